@@ -93,6 +93,12 @@ extern "C" {
         out_old_value: *mut objc_hook_lazyClassNamer,
     );
 
+    /// The hook called by `objc_getClass` and `objc_lookUpClass` when a
+    /// class isn't found. Defined in `hooks.h`.
+    #[cfg(gnustep)]
+    pub static mut _objc_lookup_class:
+        Option<unsafe extern "C" fn(name: *const c_char) -> *const crate::objc_class>;
+
     // #[deprecated = "not recommended"]
     // #[cfg(apple)]
     // pub fn _objc_flush_caches
@@ -3,11 +3,10 @@ use core::cmp::Ordering;
 use core::ffi::c_void;
 use core::marker::PhantomData;
 use core::ops::{Index, Range};
-use core::ptr::NonNull;
 
-use objc2::msg_send;
 use objc2::rc::{Id, Owned, Ownership, Shared, SliceId};
 use objc2::runtime::Object;
+use objc2::{msg_send, msg_send_id};
 
 use super::{
     INSCopying, INSFastEnumeration, INSMutableCopying, INSObject, NSComparisonResult, NSEnumerator,
@@ -16,16 +15,15 @@ use super::{
 
 unsafe fn from_refs<A: INSArray + ?Sized>(refs: &[&A::Item]) -> Id<A, A::Ownership> {
     let cls = A::class();
-    let obj: *mut A = unsafe { msg_send![cls, alloc] };
-    let obj: *mut A = unsafe {
-        msg_send![
+    let obj = unsafe { msg_send_id![cls, alloc] };
+    unsafe {
+        msg_send_id![
             obj,
             initWithObjects: refs.as_ptr(),
             count: refs.len(),
         ]
-    };
-    let obj = unsafe { NonNull::new_unchecked(obj) };
-    unsafe { Id::new(obj) }
+    }
+    .expect("Failed to initialize array")
 }
 
 pub unsafe trait INSArray: INSObject {
@@ -46,6 +44,10 @@ pub unsafe trait INSArray: INSObject {
 
     #[doc(alias = "objectAtIndex:")]
     fn get(&self, index: usize) -> Option<&Self::Item> {
+        // A `CachedImp` isn't used here, since looking up the method costs
+        // more than the single `objc_msgSend` that it would replace; loops
+        // that call `objectAtIndex:` should look it up on the array's class
+        // once (arrays are instances of private subclasses).
         // TODO: Replace this check with catching the thrown NSRangeException
         if index < self.len() {
             // SAFETY: The index is checked to be in bounds.
@@ -578,4 +580,27 @@ mod tests {
         assert_send_sync::<Id<NSArray<NSString, Shared>, Shared>>();
         assert_send_sync::<Id<NSMutableArray<NSString, Shared>, Owned>>();
     }
+
+    #[test]
+    fn test_array_with_objects() {
+        use core::ptr;
+
+        let obj = NSObject::new();
+        let obj: &NSObject = &obj;
+        let array: *mut NSArray<NSObject, Shared> = unsafe {
+            msg_send![
+                NSArray::<NSObject, Shared>::class(),
+                arrayWithObjects: obj;
+                obj,
+                obj,
+                ptr::null::<NSObject>(),
+            ]
+        };
+        let array = unsafe { &*array };
+        assert_eq!(array.len(), 3);
+        assert_eq!(
+            array.first().unwrap() as *const NSObject,
+            obj as *const NSObject
+        );
+    }
 }
@@ -1,6 +1,4 @@
-use core::ptr::NonNull;
-
-use objc2::msg_send;
+use objc2::msg_send_id;
 use objc2::rc::{Id, Owned, Ownership};
 
 use super::INSObject;
@@ -35,10 +33,7 @@ pub unsafe trait INSCopying: INSObject {
     type Output: INSObject;
 
     fn copy(&self) -> Id<Self::Output, Self::Ownership> {
-        unsafe {
-            let obj: *mut Self::Output = msg_send![self, copy];
-            Id::new(NonNull::new_unchecked(obj))
-        }
+        unsafe { msg_send_id![self, copy] }.unwrap()
     }
 }
 
@@ -50,9 +45,6 @@ pub unsafe trait INSMutableCopying: INSObject {
     type Output: INSObject;
 
     fn mutable_copy(&self) -> Id<Self::Output, Owned> {
-        unsafe {
-            let obj: *mut Self::Output = msg_send![self, mutableCopy];
-            Id::new(NonNull::new_unchecked(obj))
-        }
+        unsafe { msg_send_id![self, mutableCopy] }.unwrap()
     }
 }
@@ -1,12 +1,12 @@
 #[cfg(feature = "block")]
 use alloc::vec::Vec;
+use core::ffi::c_void;
 use core::ops::{Deref, DerefMut, Range};
 use core::slice;
-use core::{ffi::c_void, ptr::NonNull};
 
 use super::{INSCopying, INSMutableCopying, INSObject, NSRange};
-use objc2::msg_send;
 use objc2::rc::{Id, Owned, Ownership, Shared};
+use objc2::{msg_send, msg_send_id};
 
 pub unsafe trait INSData: INSObject {
     type Ownership: Ownership;
@@ -35,14 +35,13 @@ pub unsafe trait INSData: INSObject {
         let cls = Self::class();
         let bytes_ptr = bytes.as_ptr() as *const c_void;
         unsafe {
-            let obj: *mut Self = msg_send![cls, alloc];
-            let obj: *mut Self = msg_send![
-                obj,
+            msg_send_id![
+                msg_send_id![cls, alloc],
                 initWithBytes: bytes_ptr,
                 length: bytes.len(),
-            ];
-            Id::new(NonNull::new_unchecked(obj))
+            ]
         }
+        .unwrap()
     }
 
     #[cfg(feature = "block")]
@@ -80,15 +79,14 @@ pub unsafe trait INSData: INSObject {
         let mut bytes = ManuallyDrop::new(bytes);
 
         unsafe {
-            let obj: *mut Self = msg_send![cls, alloc];
-            let obj: *mut Self = msg_send![
-                obj,
+            msg_send_id![
+                msg_send_id![cls, alloc],
                 initWithBytesNoCopy: bytes.as_mut_ptr() as *mut c_void,
                 length: bytes.len(),
                 deallocator: dealloc,
-            ];
-            Id::new(NonNull::new_unchecked(obj))
+            ]
         }
+        .unwrap()
     }
 }
 
@@ -0,0 +1,474 @@
+/// Helper macro for [`declare_class!`], rewriting the methods to
+/// `extern "C"` functions, and registering them afterwards.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __inner_declare_class {
+    {@rewrite_methods} => {};
+    {
+        @rewrite_methods
+
+        $(#[$m:meta])*
+        @sel($($sel:tt)+)
+        $v:vis fn $name:ident($($args:tt)*) $(-> $ret:ty)? $body:block
+
+        $($rest:tt)*
+    } => {
+        $crate::__inner_declare_class! {
+            @rewrite_fn
+            ($(#[$m])*)
+            ($v)
+            $name
+            ($($args)*)
+            ($($ret)?)
+            $body
+        }
+
+        $crate::__inner_declare_class! {
+            @rewrite_methods
+
+            $($rest)*
+        }
+    };
+
+    // Instance method, mutable receiver
+    {
+        @rewrite_fn
+        ($($m:tt)*)
+        ($v:vis)
+        $name:ident
+        (&mut $self:ident $(, $arg:ident: $arg_ty:ty)* $(,)?)
+        ($($ret:ty)?)
+        $body:block
+    } => {
+        $($m)*
+        $v extern "C" fn $name(
+            &mut $self,
+            _: $crate::__macro_helpers::Sel,
+            $($arg: $arg_ty),*
+        ) $(-> $ret)? $body
+    };
+    // Instance method, immutable receiver
+    {
+        @rewrite_fn
+        ($($m:tt)*)
+        ($v:vis)
+        $name:ident
+        (&$self:ident $(, $arg:ident: $arg_ty:ty)* $(,)?)
+        ($($ret:ty)?)
+        $body:block
+    } => {
+        $($m)*
+        $v extern "C" fn $name(
+            &$self,
+            _: $crate::__macro_helpers::Sel,
+            $($arg: $arg_ty),*
+        ) $(-> $ret)? $body
+    };
+    // Class method
+    {
+        @rewrite_fn
+        ($($m:tt)*)
+        ($v:vis)
+        $name:ident
+        ($($arg:ident: $arg_ty:ty),* $(,)?)
+        ($($ret:ty)?)
+        $body:block
+    } => {
+        $($m)*
+        $v extern "C" fn $name(
+            _: &$crate::__macro_helpers::Class,
+            _: $crate::__macro_helpers::Sel,
+            $($arg: $arg_ty),*
+        ) $(-> $ret)? $body
+    };
+
+    {@register_methods $decl:ident} => {};
+    {
+        @register_methods $decl:ident
+
+        $(#[$m:meta])*
+        @sel($($sel:tt)+)
+        $v:vis fn $name:ident($($args:tt)*) $(-> $ret:ty)? $body:block
+
+        $($rest:tt)*
+    } => {
+        $crate::__inner_declare_class! {
+            @register_fn $decl
+            ($($sel)+)
+            $name
+            ($($args)*)
+            ($($ret)?)
+        }
+
+        $crate::__inner_declare_class! {
+            @register_methods $decl
+
+            $($rest)*
+        }
+    };
+
+    // Instance method, mutable receiver
+    {
+        @register_fn $decl:ident
+        ($($sel:tt)+)
+        $name:ident
+        (&mut $self:ident $(, $arg:ident: $arg_ty:ty)* $(,)?)
+        ($($ret:ty)?)
+    } => {
+        $decl.add_method(
+            $crate::__macro_helpers::sel!($($sel)+),
+            Self::$name as extern "C" fn(
+                &mut Self,
+                $crate::__macro_helpers::Sel,
+                $($arg_ty),*
+            ) $(-> $ret)?,
+        );
+    };
+    // Instance method, immutable receiver
+    {
+        @register_fn $decl:ident
+        ($($sel:tt)+)
+        $name:ident
+        (&$self:ident $(, $arg:ident: $arg_ty:ty)* $(,)?)
+        ($($ret:ty)?)
+    } => {
+        $decl.add_method(
+            $crate::__macro_helpers::sel!($($sel)+),
+            Self::$name as extern "C" fn(
+                &Self,
+                $crate::__macro_helpers::Sel,
+                $($arg_ty),*
+            ) $(-> $ret)?,
+        );
+    };
+    // Class method
+    {
+        @register_fn $decl:ident
+        ($($sel:tt)+)
+        $name:ident
+        ($($arg:ident: $arg_ty:ty),* $(,)?)
+        ($($ret:ty)?)
+    } => {
+        $decl.add_class_method(
+            $crate::__macro_helpers::sel!($($sel)+),
+            Self::$name as extern "C" fn(
+                &$crate::__macro_helpers::Class,
+                $crate::__macro_helpers::Sel,
+                $($arg_ty),*
+            ) $(-> $ret)?,
+        );
+    };
+}
+
+/// Declare a new Objective-C class.
+///
+/// This is mostly just a convenience macro on top of [`ClassDecl`] and the
+/// [`INSObject`] trait, but it also takes care of declaring the Rust struct
+/// that represents the class, and of registering the class lazily the first
+/// time [`INSObject::class`] is called.
+///
+/// [`ClassDecl`]: objc2::declare::ClassDecl
+/// [`INSObject`]: crate::INSObject
+/// [`INSObject::class`]: crate::INSObject::class
+///
+/// # Specification
+///
+/// This macro consists of the following parts (the first is required, the
+/// rest are optional and can be repeated):
+/// - The struct declaration.
+/// - A method implementation block `unsafe impl { ... }`.
+/// - A protocol implementation block `unsafe impl Protocol<NSCopying> { ... }`.
+///
+/// ## Struct declaration
+///
+/// The struct declaration consists of the name of the class, the superclass
+/// it inherits from (which must implement [`INSObject`]), and the instance
+/// variables of the class.
+///
+/// The instance variables are declared like normal struct fields, and are
+/// available as fields of type [`Ivar`], which can be dereferenced to get
+/// the value of the instance variable. Note that a helper type with the same
+/// name as the instance variable is emitted, so instance variable names must
+/// be unique within the module.
+///
+/// The struct also implements [`Deref`] to the superclass, so that the
+/// superclass' methods can be easily accessed.
+///
+/// [`Ivar`]: objc2::declare::Ivar
+/// [`Deref`]: core::ops::Deref
+///
+/// ## Method implementation
+///
+/// Each method must be annotated with the selector it is registered under,
+/// using `@sel(...)`. Functions taking `&self` or `&mut self` are registered
+/// as instance methods, while functions without a receiver are registered as
+/// class methods.
+///
+/// The functions are rewritten to be `extern "C"` and to take the selector
+/// as an additional (unnamed) second argument, so they can't easily be called
+/// directly from Rust; use `msg_send!` for that instead.
+///
+/// ## Protocol implementation
+///
+/// Same as the method implementation, except that the class is additionally
+/// marked as conforming to the given protocol. The protocol must exist at
+/// runtime when the class is registered.
+///
+/// # Panics
+///
+/// [`INSObject::class`] panics if a class with the same name already exists,
+/// or if any of the given protocols could not be found.
+///
+/// # Safety
+///
+/// Using this macro requires writing `unsafe` in front of the struct and the
+/// method implementations, since the following invariants must be upheld:
+/// - The instance variables' types must be valid when zero-initialized,
+///   since that is what the Objective-C runtime initializes them to.
+/// - The [`Drop`] implementations of the instance variables are not run,
+///   any cleanup must be done in a custom `dealloc` method.
+/// - The argument and return types of each method must match those that are
+///   expected when the method is invoked from Objective-C (in particular
+///   when overriding methods on the superclass, or implementing a protocol).
+/// - The method must not panic, since unwinding into Objective-C is
+///   undefined behaviour.
+///
+/// # Examples
+///
+/// ```no_run
+/// use objc2::msg_send;
+/// use objc2::rc::{Id, Owned};
+/// use objc2::runtime::Bool;
+/// use objc2_foundation::{declare_class, INSObject, NSObject};
+///
+/// declare_class! {
+///     unsafe struct MyCustomObject: NSObject {
+///         foo: u8,
+///         pub bar: u32,
+///     }
+///
+///     unsafe impl {
+///         @sel(setFoo:)
+///         fn set_foo(&mut self, foo: u8) {
+///             *self.foo = foo;
+///         }
+///
+///         @sel(foo)
+///         fn get_foo(&self) -> u8 {
+///             *self.foo
+///         }
+///
+///         @sel(myClassMethod)
+///         fn my_class_method() -> Bool {
+///             Bool::YES
+///         }
+///     }
+/// }
+///
+/// let cls = MyCustomObject::class();
+/// let mut obj: Id<MyCustomObject, Owned> = unsafe { Id::new(msg_send![cls, new]) };
+///
+/// let _: () = unsafe { msg_send![obj, setFoo: 3u8] };
+/// assert_eq!(*obj.foo, 3);
+/// *obj.bar = 42;
+/// assert_eq!(*obj.bar, 42);
+///
+/// let result: Bool = unsafe { msg_send![cls, myClassMethod] };
+/// assert!(result.is_true());
+/// ```
+#[macro_export]
+macro_rules! declare_class {
+    {
+        $(#[$m:meta])*
+        unsafe $v:vis struct $name:ident: $inherits:ty {
+            $($ivar_v:vis $ivar:ident: $ivar_ty:ty,)*
+        }
+
+        $(
+            $(#[$impl_m:meta])*
+            unsafe impl $(Protocol<$protocol:ident>)? {
+                $($methods:tt)*
+            }
+        )*
+    } => {
+        $(
+            #[allow(non_camel_case_types)]
+            #[doc(hidden)]
+            $ivar_v struct $ivar {
+                __priv: (),
+            }
+
+            unsafe impl $crate::__macro_helpers::IvarType for $ivar {
+                type Type = $ivar_ty;
+                const NAME: &'static str = stringify!($ivar);
+            }
+        )*
+
+        $(#[$m])*
+        #[repr(C)]
+        $v struct $name {
+            __inner: $inherits,
+            $($ivar_v $ivar: $crate::__macro_helpers::Ivar<$ivar>,)*
+        }
+
+        unsafe impl $crate::__macro_helpers::Message for $name {}
+
+        unsafe impl $crate::__macro_helpers::RefEncode for $name {
+            const ENCODING_REF: $crate::__macro_helpers::Encoding<'static>
+                = $crate::__macro_helpers::Encoding::Object;
+        }
+
+        unsafe impl $crate::INSObject for $name {
+            fn class() -> &'static $crate::__macro_helpers::Class {
+                use $crate::__macro_helpers::{ClassDecl, Once};
+
+                static REGISTER_CLASS: Once = Once::new();
+
+                REGISTER_CLASS.call_once(|| {
+                    let superclass = <$inherits as $crate::INSObject>::class();
+                    let mut decl = ClassDecl::new(stringify!($name), superclass).unwrap_or_else(|| {
+                        panic!(
+                            "could not create new class {}. Perhaps a class with that name already exists?",
+                            stringify!($name),
+                        )
+                    });
+
+                    $(
+                        decl.add_ivar::<<$ivar as $crate::__macro_helpers::IvarType>::Type>(
+                            <$ivar as $crate::__macro_helpers::IvarType>::NAME,
+                        );
+                    )*
+
+                    $(
+                        $(
+                            let proto = $crate::__macro_helpers::Protocol::get(stringify!($protocol)).unwrap_or_else(|| {
+                                panic!(
+                                    "could not find protocol {}",
+                                    stringify!($protocol),
+                                )
+                            });
+                            decl.add_protocol(proto);
+                        )?
+
+                        // SAFETY: Upheld by caller
+                        #[allow(unused_unsafe)]
+                        unsafe {
+                            $crate::__inner_declare_class! {
+                                @register_methods decl
+
+                                $($methods)*
+                            }
+                        }
+                    )*
+
+                    let _cls = decl.register();
+                });
+
+                $crate::__macro_helpers::class!($name)
+            }
+        }
+
+        impl ::core::ops::Deref for $name {
+            type Target = $inherits;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                &self.__inner
+            }
+        }
+
+        impl ::core::ops::DerefMut for $name {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.__inner
+            }
+        }
+
+        $(
+            $(#[$impl_m])*
+            impl $name {
+                $crate::__inner_declare_class! {
+                    @rewrite_methods
+
+                    $($methods)*
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use objc2::rc::{Id, Owned};
+    use objc2::runtime::Bool;
+    use objc2::{msg_send, sel};
+
+    use crate::{INSObject, NSObject};
+
+    declare_class! {
+        unsafe struct DeclareClassTestObject: NSObject {
+            number: u32,
+            flag: Bool,
+        }
+
+        unsafe impl {
+            @sel(setNumber:)
+            fn set_number(&mut self, number: u32) {
+                *self.number = number;
+            }
+
+            @sel(number)
+            fn get_number(&self) -> u32 {
+                *self.number
+            }
+
+            @sel(addNumber:toNumber:)
+            fn add_numbers(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        }
+    }
+
+    fn new_object() -> Id<DeclareClassTestObject, Owned> {
+        let cls = DeclareClassTestObject::class();
+        unsafe { Id::new(msg_send![cls, new]) }
+    }
+
+    #[test]
+    fn test_ivars() {
+        let mut obj = new_object();
+        assert_eq!(*obj.number, 0);
+        assert!(obj.flag.is_false());
+
+        *obj.number = 5;
+        *obj.flag = Bool::YES;
+        assert_eq!(*obj.number, 5);
+        assert!(obj.flag.is_true());
+    }
+
+    #[test]
+    fn test_methods() {
+        let obj = new_object();
+        let _: () = unsafe { msg_send![obj, setNumber: 42u32] };
+        let result: u32 = unsafe { msg_send![obj, number] };
+        assert_eq!(result, 42);
+        assert_eq!(*obj.number, 42);
+    }
+
+    #[test]
+    fn test_class_method() {
+        let cls = DeclareClassTestObject::class();
+        let result: u32 = unsafe { msg_send![cls, addNumber: 3u32, toNumber: 4u32] };
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn test_superclass() {
+        let cls = DeclareClassTestObject::class();
+        assert_eq!(cls.superclass().unwrap(), NSObject::class());
+        assert!(cls.instance_method(sel!(setNumber:)).is_some());
+
+        let obj = new_object();
+        assert!(obj.is_kind_of(NSObject::class()));
+    }
+}
@@ -4,8 +4,8 @@ use core::marker::PhantomData;
 use core::ops::Index;
 use core::ptr::{self, NonNull};
 
-use objc2::msg_send;
 use objc2::rc::{Id, Owned, Ownership, Shared, SliceId};
+use objc2::{msg_send, msg_send_id};
 
 use super::{INSCopying, INSFastEnumeration, INSObject, NSArray, NSEnumerator};
 
@@ -16,17 +16,16 @@ where
 {
     let cls = D::class();
     let count = min(keys.len(), vals.len());
-    let obj: *mut D = unsafe { msg_send![cls, alloc] };
-    let obj: *mut D = unsafe {
-        msg_send![
+    let obj = unsafe { msg_send_id![cls, alloc] };
+    unsafe {
+        msg_send_id![
             obj,
             initWithObjects: vals.as_ptr(),
             forKeys: keys.as_ptr(),
             count: count,
         ]
-    };
-    let obj = unsafe { NonNull::new_unchecked(obj) };
-    unsafe { Id::new(obj) }
+    }
+    .expect("Failed to initialize dictionary")
 }
 
 pub unsafe trait INSDictionary: INSObject {
@@ -0,0 +1,99 @@
+use alloc::borrow::ToOwned;
+use core::fmt;
+use core::ptr;
+use std::error::Error;
+
+use objc2::ffi::NSInteger;
+use objc2::rc::{autoreleasepool, Id, Shared};
+use objc2::runtime::Object;
+use objc2::{msg_send, msg_send_id};
+
+use super::{INSObject, INSString, NSString};
+
+pub unsafe trait INSError: INSObject {
+    /// Creates an error with the given domain and code, and no user info.
+    fn new(domain: &NSString, code: NSInteger) -> Id<Self, Shared>
+    where
+        Self: Sized,
+    {
+        let cls = Self::class();
+        let user_info: *const Object = ptr::null();
+        unsafe {
+            msg_send_id![
+                cls,
+                errorWithDomain: domain,
+                code: code,
+                userInfo: user_info,
+            ]
+        }
+        .unwrap()
+    }
+
+    fn code(&self) -> NSInteger {
+        unsafe { msg_send![self, code] }
+    }
+
+    fn domain(&self) -> Id<NSString, Shared> {
+        unsafe { msg_send_id![self, domain] }.unwrap()
+    }
+
+    #[doc(alias = "localizedDescription")]
+    fn localized_description(&self) -> Id<NSString, Shared> {
+        unsafe { msg_send_id![self, localizedDescription] }.unwrap()
+    }
+}
+
+object!(unsafe pub struct NSError);
+
+// SAFETY: `NSError` is immutable, and documented to be thread safe.
+unsafe impl Sync for NSError {}
+unsafe impl Send for NSError {}
+
+unsafe impl INSError for NSError {}
+
+impl fmt::Display for NSError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = self.localized_description();
+        // Same workaround as in `NSString`'s `Display` implementation.
+        let s = autoreleasepool(|pool| description.as_str(pool).to_owned());
+        fmt::Display::fmt(&s, f)
+    }
+}
+
+impl Error for NSError {}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::NonNull;
+
+    use super::*;
+    use crate::NSData;
+    use alloc::format;
+    use objc2::msg_send_err;
+
+    #[test]
+    fn test_new() {
+        let domain = NSString::from_str("TestDomain");
+        let error = NSError::new(&domain, 42);
+        assert_eq!(error.code(), 42);
+        assert_eq!(*error.domain(), *domain);
+        assert!(!format!("{}", error).is_empty());
+    }
+
+    #[test]
+    fn test_msg_send_err() {
+        let cls = NSData::class();
+
+        let path = NSString::from_str(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+        let res: Result<NonNull<NSData>, Option<Id<NSError, Shared>>> = unsafe {
+            msg_send_err![cls, dataWithContentsOfFile: &*path, options: 0usize, error: _]
+        };
+        assert!(res.is_ok());
+
+        let path = NSString::from_str("/this/file/does/not/exist");
+        let res: Result<NonNull<NSData>, Option<Id<NSError, Shared>>> = unsafe {
+            msg_send_err![cls, dataWithContentsOfFile: &*path, options: 0usize, error: _]
+        };
+        assert_ne!(res.unwrap_err().unwrap().code(), 0);
+    }
+}
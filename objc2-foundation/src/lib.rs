@@ -20,11 +20,20 @@ pub use self::copying::{INSCopying, INSMutableCopying};
 pub use self::data::{INSData, INSMutableData, NSData, NSMutableData};
 pub use self::dictionary::{INSDictionary, NSDictionary};
 pub use self::enumerator::{INSFastEnumeration, NSEnumerator, NSFastEnumerator};
+pub use self::error::{INSError, NSError};
 pub use self::object::{INSObject, NSObject};
 pub use self::range::NSRange;
 pub use self::string::{INSString, NSString};
 pub use self::value::{INSValue, NSValue};
 
+#[doc(hidden)]
+pub mod __macro_helpers {
+    pub use objc2::declare::{ClassDecl, Ivar, IvarType};
+    pub use objc2::runtime::{Class, Protocol, Sel};
+    pub use objc2::{class, sel, Encoding, Message, RefEncode};
+    pub use std::sync::Once;
+}
+
 #[cfg(apple)]
 #[link(name = "Foundation", kind = "framework")]
 extern "C" {}
@@ -40,8 +49,10 @@ mod array;
 mod comparison_result;
 mod copying;
 mod data;
+mod declare_class;
 mod dictionary;
 mod enumerator;
+mod error;
 mod object;
 mod range;
 mod string;
@@ -100,7 +100,7 @@ macro_rules! unsafe_def_fn {
     ($v:vis fn new -> $o:ty) => {
         $v fn new() -> Id<Self, $o> {
             let cls = <Self as INSObject>::class();
-            unsafe { Id::new(NonNull::new_unchecked(msg_send![cls, new])) }
+            unsafe { ::objc2::msg_send_id![cls, new] }.unwrap()
         }
     };
 }
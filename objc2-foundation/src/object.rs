@@ -1,10 +1,9 @@
 use core::marker::PhantomData;
-use core::ptr::NonNull;
 
-use objc2::msg_send;
 use objc2::rc::{Id, Owned, Shared};
 use objc2::runtime::{Bool, Class, Object};
 use objc2::Message;
+use objc2::{msg_send, msg_send_id};
 
 use super::NSString;
 
@@ -21,11 +20,8 @@ pub unsafe trait INSObject: Message {
     }
 
     fn description(&self) -> Id<NSString, Shared> {
-        unsafe {
-            let result: *mut NSString = msg_send![self, description];
-            // TODO: Verify that description always returns a non-null string
-            Id::retain(NonNull::new_unchecked(result))
-        }
+        // TODO: Verify that description always returns a non-null string
+        unsafe { msg_send_id![self, description] }.unwrap()
     }
 
     fn is_kind_of(&self, cls: &Class) -> bool {
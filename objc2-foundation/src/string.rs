@@ -1,15 +1,14 @@
 use core::ffi::c_void;
 use core::fmt;
-use core::ptr::NonNull;
 use core::slice;
 use core::str;
 use std::os::raw::c_char;
 
 use alloc::borrow::ToOwned;
 use objc2::ffi;
-use objc2::msg_send;
 use objc2::rc::{autoreleasepool, AutoreleasePool};
 use objc2::rc::{Id, Shared};
+use objc2::{msg_send, msg_send_id};
 
 use super::{INSCopying, INSObject};
 
@@ -89,15 +88,14 @@ pub unsafe trait INSString: INSObject {
         let cls = Self::class();
         let bytes = string.as_ptr() as *const c_void;
         unsafe {
-            let obj: *mut Self = msg_send![cls, alloc];
-            let obj: *mut Self = msg_send![
-                obj,
+            msg_send_id![
+                msg_send_id![cls, alloc],
                 initWithBytes: bytes,
                 length: string.len(),
                 encoding: UTF8_ENCODING,
-            ];
-            Id::new(NonNull::new_unchecked(obj))
+            ]
         }
+        .unwrap()
     }
 }
 
@@ -238,4 +236,39 @@ mod tests {
         });
         assert_eq!(ns_string.len(), expected.len());
     }
+
+    #[test]
+    fn test_string_with_format() {
+        use std::ffi::CStr;
+        use std::os::raw::c_int;
+
+        let format = NSString::from_str("%d %.1f %s");
+        let s = CStr::from_bytes_with_nul(b"foo\0").unwrap();
+        let string: *mut NSString = unsafe {
+            msg_send![
+                NSString::class(),
+                stringWithFormat: &*format;
+                -42 as c_int,
+                f64::from(1.5f32),
+                s.as_ptr(),
+            ]
+        };
+        let string = unsafe { &*string };
+        autoreleasepool(|pool| {
+            assert_eq!(string.as_str(pool), "-42 1.5 foo");
+        });
+    }
+
+    #[test]
+    fn test_try_msg_send_variadic() {
+        use objc2::try_msg_send;
+
+        let format = NSString::from_str("%lu");
+        let result: Result<*mut NSString, _> =
+            unsafe { try_msg_send![NSString::class(), stringWithFormat: &*format; 7usize] };
+        let string = unsafe { &*result.unwrap() };
+        autoreleasepool(|pool| {
+            assert_eq!(string.as_str(pool), "7");
+        });
+    }
 }
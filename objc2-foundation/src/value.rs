@@ -7,9 +7,9 @@ use core::str;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
-use objc2::msg_send;
 use objc2::rc::{Id, Shared};
 use objc2::Encode;
+use objc2::{msg_send, msg_send_id};
 
 use super::{INSCopying, INSObject};
 
@@ -61,14 +61,13 @@ pub unsafe trait INSValue: INSObject {
         let bytes = &value as *const Self::Value as *const c_void;
         let encoding = CString::new(Self::Value::ENCODING.to_string()).unwrap();
         unsafe {
-            let obj: *mut Self = msg_send![cls, alloc];
-            let obj: *mut Self = msg_send![
-                obj,
+            msg_send_id![
+                msg_send_id![cls, alloc],
                 initWithBytes: bytes,
                 objCType: encoding.as_ptr(),
-            ];
-            Id::new(NonNull::new_unchecked(obj))
+            ]
         }
+        .unwrap()
     }
 }
 
@@ -0,0 +1,25 @@
+//! Print the declarations of the given classes and protocols, as
+//! reconstructed from the runtime metadata.
+//!
+//! Usage: `cargo run --example class_dump --features malloc -- NSObject NSCopying`
+use std::env;
+
+use objc2::dump::{ClassInterface, ProtocolInterface};
+use objc2::runtime::{Class, Protocol};
+
+fn main() {
+    let mut names: Vec<String> = env::args().skip(1).collect();
+    if names.is_empty() {
+        names.push("NSObject".into());
+    }
+
+    for name in names {
+        if let Some(cls) = Class::get(&name) {
+            println!("{}", ClassInterface::new(cls));
+        } else if let Some(proto) = Protocol::get(&name) {
+            println!("{}", ProtocolInterface::new(proto));
+        } else {
+            eprintln!("No class or protocol named {:?} was found", name);
+        }
+    }
+}
@@ -45,4 +45,9 @@ fn main() {
     // Invoke a method on the object
     let hash: usize = unsafe { msg_send![obj, hash] };
     println!("NSObject hash: {}", hash);
+
+    // Print the declaration of the class, reconstructed from the above
+    // information (see also the `class_dump` example)
+    #[cfg(feature = "malloc")]
+    println!("{}", objc2::dump::ClassInterface::new(cls));
 }
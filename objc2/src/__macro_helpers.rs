@@ -0,0 +1,374 @@
+use core::ptr::NonNull;
+
+use crate::rc::{Allocated, Id, Ownership, Shared};
+use crate::runtime::{Bool, Sel};
+use crate::{Encode, Message, MessageArguments, MessageReceiver, RefEncode, SendError};
+
+/// The memory management semantics of a selector, as determined by which
+/// method family it belongs to.
+///
+/// See the [clang documentation][families] for the rules.
+///
+/// [families]: https://clang.llvm.org/docs/AutomaticReferenceCounting.html#method-families
+pub struct RetainSemantics<
+    // `new` family
+    const NEW: bool,
+    // `alloc` family
+    const ALLOC: bool,
+    // `init` family
+    const INIT: bool,
+    // `copy` or `mutableCopy` family
+    const COPY: bool,
+> {}
+
+pub trait MsgSendId<T, U> {
+    unsafe fn send_message_id<A: MessageArguments>(
+        obj: T,
+        sel: Sel,
+        args: A,
+    ) -> Result<U, SendError>;
+}
+
+// `new`, `copy` and `mutableCopy` return a retained object.
+macro_rules! impl_retained {
+    ($new:literal, $copy:literal) => {
+        impl<T, U, O> MsgSendId<T, Option<Id<U, O>>> for RetainSemantics<$new, false, false, $copy>
+        where
+            T: MessageReceiver,
+            U: Message + ?Sized,
+            O: Ownership,
+        {
+            #[inline(always)]
+            unsafe fn send_message_id<A: MessageArguments>(
+                obj: T,
+                sel: Sel,
+                args: A,
+            ) -> Result<Option<Id<U, O>>, SendError> {
+                let ptr: *mut U = unsafe { obj.send_message(sel, args) }?;
+                // SAFETY: The selector is in a family that returns +1
+                Ok(NonNull::new(ptr).map(|ptr| unsafe { Id::new(ptr) }))
+            }
+        }
+    };
+}
+
+impl_retained!(true, false);
+impl_retained!(false, true);
+
+// `alloc` returns a retained, but uninitialized object.
+impl<T: MessageReceiver, U: Message + ?Sized> MsgSendId<T, Option<Allocated<U>>>
+    for RetainSemantics<false, true, false, false>
+{
+    #[inline(always)]
+    unsafe fn send_message_id<A: MessageArguments>(
+        obj: T,
+        sel: Sel,
+        args: A,
+    ) -> Result<Option<Allocated<U>>, SendError> {
+        let ptr: *mut U = unsafe { obj.send_message(sel, args) }?;
+        // SAFETY: The selector is in the `alloc` family
+        Ok(unsafe { Allocated::new(ptr) })
+    }
+}
+
+// `init` consumes the receiver, and returns a retained object. If it fails,
+// the receiver has already been released by the method.
+impl<T: Message + ?Sized, O: Ownership> MsgSendId<Option<Allocated<T>>, Option<Id<T, O>>>
+    for RetainSemantics<false, false, true, false>
+{
+    #[inline(always)]
+    unsafe fn send_message_id<A: MessageArguments>(
+        obj: Option<Allocated<T>>,
+        sel: Sel,
+        args: A,
+    ) -> Result<Option<Id<T, O>>, SendError> {
+        match obj {
+            // SAFETY: The selector is in the `init` family
+            Some(obj) => unsafe { obj.send_init_unchecked(sel, args) },
+            // Messages to nil return nil
+            None => Ok(None),
+        }
+    }
+}
+
+// All other selectors return an autoreleased object, which we retain.
+impl<T: MessageReceiver, U: Message, O: Ownership> MsgSendId<T, Option<Id<U, O>>>
+    for RetainSemantics<false, false, false, false>
+{
+    #[inline(always)]
+    unsafe fn send_message_id<A: MessageArguments>(
+        obj: T,
+        sel: Sel,
+        args: A,
+    ) -> Result<Option<Id<U, O>>, SendError> {
+        let ptr: *mut U = unsafe { obj.send_message(sel, args) }?;
+        // SAFETY: The object is valid since it was just returned, and the
+        // caller upholds the ownership rules.
+        Ok(NonNull::new(ptr).map(|ptr| unsafe { Id::retain(ptr) }))
+    }
+}
+
+/// The successful results of methods that report errors through an
+/// `NSError **` out-parameter, used by [`msg_send_err!`][crate::msg_send_err].
+///
+/// Following Cocoa's conventions, the method failed if it returned `NO` or
+/// `nil`.
+pub trait ErrorReturn: Sized {
+    /// The type that the method returns.
+    type Raw: Encode;
+
+    /// Returns [`None`] if the method failed.
+    ///
+    /// # Safety
+    ///
+    /// The returned value must be valid for the lifetime of `Self`.
+    unsafe fn from_raw(raw: Self::Raw) -> Option<Self>;
+}
+
+// Methods returning `BOOL`
+impl ErrorReturn for () {
+    type Raw = Bool;
+
+    #[inline]
+    unsafe fn from_raw(raw: Bool) -> Option<()> {
+        if raw.is_true() {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+// Methods returning an object
+impl<T: RefEncode> ErrorReturn for NonNull<T> {
+    type Raw = *mut T;
+
+    #[inline]
+    unsafe fn from_raw(raw: *mut T) -> Option<Self> {
+        NonNull::new(raw)
+    }
+}
+
+impl<T: RefEncode> ErrorReturn for &T {
+    type Raw = *mut T;
+
+    #[inline]
+    unsafe fn from_raw(raw: *mut T) -> Option<Self> {
+        unsafe { raw.as_ref() }
+    }
+}
+
+/// Converts the result of a method with an `NSError **` out-parameter.
+///
+/// # Safety
+///
+/// The error must be null or a valid object, and the caller must uphold the
+/// safety requirements of [`ErrorReturn::from_raw`].
+#[inline]
+pub unsafe fn error_result<T, E>(raw: T::Raw, error: *mut E) -> Result<T, Option<Id<E, Shared>>>
+where
+    T: ErrorReturn,
+    E: Message,
+{
+    match unsafe { T::from_raw(raw) } {
+        Some(ok) => Ok(ok),
+        // SAFETY: The error is autoreleased, so we retain it.
+        None => Err(NonNull::new(error).map(|error| unsafe { Id::retain(error) })),
+    }
+}
+
+/// Checks whether the selector is in the given method family.
+///
+/// A selector is in a family if its name, ignoring leading underscores,
+/// starts with the family name, and the family name is not immediately
+/// followed by a lowercase letter (so e.g. `initWithFoo:` and `init` are in
+/// the `init` family, but `initialize` is not).
+pub const fn in_selector_family(mut selector: &[u8], mut family: &[u8]) -> bool {
+    while let [b'_', rest @ ..] = selector {
+        selector = rest;
+    }
+
+    loop {
+        match (selector, family) {
+            // The whole family name matched
+            (_, []) => {
+                return match selector {
+                    [byte, ..] => !byte.is_ascii_lowercase(),
+                    [] => true,
+                };
+            }
+            ([s, selector_rest @ ..], [f, family_rest @ ..]) if *s == *f => {
+                selector = selector_rest;
+                family = family_rest;
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::{Owned, Shared};
+    use crate::runtime::Object;
+
+    #[test]
+    fn test_in_selector_family() {
+        assert!(in_selector_family(b"alloc", b"alloc"));
+        assert!(in_selector_family(b"allocWithZone:", b"alloc"));
+        assert!(!in_selector_family(b"dealloc", b"alloc"));
+        assert!(!in_selector_family(b"allocated", b"alloc"));
+
+        assert!(in_selector_family(b"init", b"init"));
+        assert!(in_selector_family(b"init:", b"init"));
+        assert!(in_selector_family(b"initWithFoo:bar:", b"init"));
+        assert!(in_selector_family(b"_init", b"init"));
+        assert!(in_selector_family(b"__initFoo", b"init"));
+        assert!(!in_selector_family(b"initialize", b"init"));
+        assert!(!in_selector_family(b"ini", b"init"));
+        assert!(!in_selector_family(b"", b"init"));
+
+        assert!(in_selector_family(b"copy", b"copy"));
+        assert!(in_selector_family(b"copyWithZone:", b"copy"));
+        assert!(!in_selector_family(b"copyright", b"copy"));
+        assert!(!in_selector_family(b"mutableCopy", b"copy"));
+        assert!(in_selector_family(b"mutableCopy", b"mutableCopy"));
+
+        assert!(in_selector_family(b"new", b"new"));
+        assert!(in_selector_family(b"new_", b"new"));
+        assert!(!in_selector_family(b"newly", b"new"));
+        assert!(!in_selector_family(b"renew", b"new"));
+    }
+
+    fn retain_count(obj: &Object) -> usize {
+        unsafe { msg_send![obj, retainCount] }
+    }
+
+    #[test]
+    fn test_msg_send_id() {
+        let cls = class!(NSObject);
+        let obj: Id<Object, Owned> = unsafe { msg_send_id![cls, new] }.unwrap();
+        assert_eq!(retain_count(&obj), 1);
+
+        let obj: Option<Allocated<Object>> = unsafe { msg_send_id![cls, alloc] };
+        let obj: Id<Object, Owned> = unsafe { msg_send_id![obj, init] }.unwrap();
+        assert_eq!(retain_count(&obj), 1);
+
+        // Borrowed `Id`s can be used as receivers, and other selectors
+        // retain the returned object
+        let obj: Id<Object, Shared> = obj.into();
+        let same: Id<Object, Shared> = unsafe { msg_send_id![&obj, self] }.unwrap();
+        assert_eq!(&*same as *const Object, &*obj as *const Object);
+        assert_eq!(retain_count(&obj), 2);
+        drop(same);
+        assert_eq!(retain_count(&obj), 1);
+
+        // Messages to nil
+        let nil: Option<Allocated<Object>> = None;
+        let res: Option<Id<Object, Owned>> = unsafe { msg_send_id![nil, init] };
+        assert!(res.is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "verify_message")]
+    fn test_msg_send_id_init_verification_error() {
+        use crate::ffi;
+
+        let obj: Allocated<Object> = unsafe { msg_send_id![class!(NSObject), alloc] }.unwrap();
+        let ptr = Allocated::as_ptr(&obj) as *mut Object;
+        // Keep the object alive to check that it was released
+        unsafe { ffi::objc_retain(ptr as *mut ffi::objc_object) };
+
+        let res: Result<Option<Id<Object, Owned>>, _> = unsafe {
+            <RetainSemantics<false, false, true, false> as MsgSendId<_, _>>::send_message_id(
+                Some(obj),
+                sel!(initWithFoo:),
+                (5u32,),
+            )
+        };
+        assert!(matches!(res, Err(SendError::Verification(_))));
+        assert_eq!(retain_count(unsafe { &*ptr }), 1);
+        unsafe { ffi::objc_release(ptr as *mut ffi::objc_object) };
+    }
+
+    fn error_class() -> &'static crate::runtime::Class {
+        use crate::declare::ClassDecl;
+        use crate::runtime::Class;
+
+        // Returns `arg` if successful, or sets the error to `NSObject`.
+        extern "C" fn bool_or_error(
+            _this: &Class,
+            _cmd: Sel,
+            arg: Bool,
+            error: *mut *mut Object,
+        ) -> Bool {
+            if arg.is_false() {
+                unsafe { *error = class!(NSObject) as *const _ as *mut Object };
+            }
+            arg
+        }
+
+        extern "C" fn object_or_error(
+            this: &Class,
+            _cmd: Sel,
+            arg: Bool,
+            error: *mut *mut Object,
+        ) -> *const Class {
+            if arg.is_true() {
+                this
+            } else {
+                unsafe { *error = class!(NSObject) as *const _ as *mut Object };
+                core::ptr::null()
+            }
+        }
+
+        // Fails without setting the error.
+        extern "C" fn fail_without_error(
+            _this: &Class,
+            _cmd: Sel,
+            _error: *mut *mut Object,
+        ) -> Bool {
+            Bool::NO
+        }
+
+        static REGISTER: std::sync::Once = std::sync::Once::new();
+        REGISTER.call_once(|| {
+            let mut decl = ClassDecl::new("MsgSendErrObject", class!(NSObject)).unwrap();
+            unsafe {
+                let f: extern "C" fn(&Class, Sel, Bool, *mut *mut Object) -> Bool = bool_or_error;
+                decl.add_class_method(sel!(boolWith:error:), f);
+                let f: extern "C" fn(&Class, Sel, *mut *mut Object) -> Bool = fail_without_error;
+                decl.add_class_method(sel!(failWithoutError:), f);
+                let f: extern "C" fn(&Class, Sel, Bool, *mut *mut Object) -> *const Class =
+                    object_or_error;
+                decl.add_class_method(sel!(objectWith:error:), f);
+            }
+            decl.register();
+        });
+        class!(MsgSendErrObject)
+    }
+
+    #[test]
+    fn test_msg_send_err() {
+        let cls = error_class();
+        let res: Result<(), Option<Id<Object, Shared>>> =
+            unsafe { msg_send_err![cls, boolWith: Bool::YES, error: _] };
+        assert!(res.is_ok());
+        let res: Result<(), Option<Id<Object, Shared>>> =
+            unsafe { msg_send_err![cls, boolWith: Bool::NO, error: _] };
+        let err = res.unwrap_err().unwrap();
+        assert_eq!(&*err as *const Object as *const _, class!(NSObject));
+
+        let res: Result<&crate::runtime::Class, Option<Id<Object, Shared>>> =
+            unsafe { msg_send_err![cls, objectWith: Bool::YES, error: _] };
+        assert_eq!(res.unwrap(), cls);
+        let res: Result<NonNull<crate::runtime::Class>, Option<Id<Object, Shared>>> =
+            unsafe { msg_send_err![cls, objectWith: Bool::NO, error: _] };
+        assert!(res.unwrap_err().is_some());
+
+        // Failing without an error doesn't panic
+        let res: Result<(), Option<Id<Object, Shared>>> =
+            unsafe { msg_send_err![cls, failWithoutError: _] };
+        assert!(res.unwrap_err().is_none());
+    }
+}
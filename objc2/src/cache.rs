@@ -3,7 +3,7 @@ use core::ptr;
 use core::sync::atomic::{AtomicPtr, Ordering};
 
 use crate::ffi;
-use crate::runtime::{Class, Sel};
+use crate::runtime::{Class, Protocol, Sel};
 
 /// Allows storing a [`Sel`] in a static and lazily loading it.
 #[doc(hidden)]
@@ -66,3 +66,35 @@ impl CachedClass {
         }
     }
 }
+
+/// Allows storing a [`Protocol`] reference in a static and lazily loading it.
+#[doc(hidden)]
+pub struct CachedProtocol {
+    ptr: AtomicPtr<Protocol>,
+}
+
+impl CachedProtocol {
+    /// Constructs a new [`CachedProtocol`].
+    pub const fn new() -> CachedProtocol {
+        CachedProtocol {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the cached protocol. If no protocol is yet cached, gets one
+    /// with the given name and stores it.
+    #[inline(always)]
+    #[doc(hidden)]
+    pub unsafe fn get(&self, name: &str) -> Option<&'static Protocol> {
+        // `Relaxed` should be fine since `objc_getProtocol` is thread-safe.
+        let ptr = self.ptr.load(Ordering::Relaxed);
+        if ptr.is_null() {
+            let proto =
+                unsafe { ffi::objc_getProtocol(name.as_ptr() as *const _) } as *const Protocol;
+            self.ptr.store(proto as *mut _, Ordering::Relaxed);
+            unsafe { proto.as_ref() }
+        } else {
+            Some(unsafe { &*ptr })
+        }
+    }
+}
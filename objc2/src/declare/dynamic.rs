@@ -0,0 +1,177 @@
+use alloc::format;
+use std::sync::Mutex;
+
+use super::ClassDecl;
+use crate::runtime::{Class, Object};
+
+// Serializes creation of dynamic subclasses, so that two threads don't try
+// to declare the same one at the same time.
+static LOCK: Mutex<()> = Mutex::new(());
+
+impl ClassDecl {
+    /// Returns a subclass of `superclass` named `prefix` followed by the
+    /// name of the superclass, declaring and registering it the first time.
+    ///
+    /// The `declare` closure is only called when the subclass is declared,
+    /// and may add methods and protocols to it, but not instance variables,
+    /// since the subclass is meant to be used with [`Object::set_class`] (see
+    /// [`ClassDecl::isa_swizzle`]).
+    ///
+    /// The closure is called while holding a lock that serializes the
+    /// creation of dynamic subclasses, so calling this function or
+    /// [`ClassDecl::isa_swizzle`] from inside it deadlocks.
+    ///
+    /// # Panics
+    ///
+    /// If a class with the subclass' name already exists, but is not a
+    /// direct subclass of `superclass`, or if the closure added instance
+    /// variables.
+    pub fn dynamic_subclass<F>(
+        superclass: &'static Class,
+        prefix: &str,
+        declare: F,
+    ) -> &'static Class
+    where
+        F: FnOnce(&mut ClassDecl),
+    {
+        let name = format!("{}{}", prefix, superclass.name());
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(cls) = Class::get(&name) {
+            assert_eq!(
+                cls.superclass(),
+                Some(superclass),
+                "Class {} exists, but is not a subclass of {:?}",
+                name,
+                superclass,
+            );
+            return cls;
+        }
+
+        let mut decl = ClassDecl::new(&name, superclass)
+            .unwrap_or_else(|| panic!("Failed to declare dynamic subclass {}", name));
+        declare(&mut decl);
+        let instance_size = unsafe { &*decl.cls }.instance_size();
+        assert_eq!(
+            instance_size,
+            superclass.instance_size(),
+            "Dynamic subclass {} must not add instance variables",
+            name,
+        );
+        decl.register()
+    }
+
+    /// Switches the class of the object to a dynamic subclass of its current
+    /// class, and returns that subclass.
+    ///
+    /// This allows overriding methods on a single instance, without
+    /// affecting every other instance of the class, and is the same
+    /// technique that Key-Value Observing uses.
+    ///
+    /// The subclass is created by [`ClassDecl::dynamic_subclass`] with the
+    /// given prefix and closure (so it is only declared once). If the
+    /// object's class is already named `prefix` followed by the name of its
+    /// superclass, the object is assumed to already have been swizzled and is
+    /// left as-is.
+    ///
+    /// The original class can be restored with [`Object::set_class`].
+    ///
+    /// # Panics
+    ///
+    /// See [`ClassDecl::dynamic_subclass`], which also describes why this
+    /// must not be called from inside the closure.
+    ///
+    /// # Safety
+    ///
+    /// The methods added by the closure must be valid to call on the object,
+    /// and no other code may be changing the class of the object at the same
+    /// time.
+    pub unsafe fn isa_swizzle<F>(obj: &Object, prefix: &str, declare: F) -> &'static Class
+    where
+        F: FnOnce(&mut ClassDecl),
+    {
+        // SAFETY: Classes are never deallocated while they have instances.
+        let cls: &'static Class = unsafe { &*(obj.class() as *const Class) };
+        if let Some(superclass) = cls.superclass() {
+            if cls.name() == format!("{}{}", prefix, superclass.name()) {
+                return cls;
+            }
+        }
+        let subclass = ClassDecl::dynamic_subclass(cls, prefix, declare);
+        // SAFETY: The subclass has the same instance layout, and the caller
+        // upholds the rest.
+        unsafe { obj.set_class(subclass) };
+        subclass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::runtime::Sel;
+    use crate::test_utils;
+
+    extern "C" fn swizzled_foo(_this: &Object, _cmd: Sel) -> u32 {
+        100
+    }
+
+    #[test]
+    fn test_isa_swizzle() {
+        let cls = test_utils::custom_class();
+        let mut obj = test_utils::custom_object();
+        let other = test_utils::custom_object();
+        let _: () = unsafe { msg_send![obj, setFoo: 3u32] };
+        let _: () = unsafe { msg_send![other, setFoo: 4u32] };
+
+        let declared = Cell::new(0);
+        let declare = |decl: &mut ClassDecl| {
+            declared.set(declared.get() + 1);
+            let imp: extern "C" fn(&Object, Sel) -> u32 = swizzled_foo;
+            unsafe { decl.add_method(sel!(foo), imp) };
+        };
+
+        let subclass = unsafe { ClassDecl::isa_swizzle(&obj, "Swizzled_", declare) };
+        assert_eq!(subclass.name(), "Swizzled_CustomObject");
+        assert_eq!(subclass.superclass(), Some(cls));
+        assert_eq!(obj.class(), subclass);
+        assert_eq!(other.class(), cls);
+
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 100);
+        let result: u32 = unsafe { msg_send![other, foo] };
+        assert_eq!(result, 4);
+
+        // Swizzling again doesn't declare a new class
+        let again = unsafe { ClassDecl::isa_swizzle(&obj, "Swizzled_", declare) };
+        assert_eq!(again, subclass);
+        let again = unsafe { ClassDecl::isa_swizzle(&other, "Swizzled_", declare) };
+        assert_eq!(again, subclass);
+        assert_eq!(declared.get(), 1);
+
+        // A class whose name merely starts with the prefix is still swizzled
+        let prefixed = test_utils::custom_object();
+        let subclass = unsafe { ClassDecl::isa_swizzle(&prefixed, "Custom", |_| {}) };
+        assert_eq!(subclass.name(), "CustomCustomObject");
+        assert_eq!(subclass.superclass(), Some(cls));
+        assert_eq!(prefixed.class(), subclass);
+
+        // Restore the original class; the ivars are left untouched
+        let previous = unsafe { obj.set_class(cls) };
+        assert_eq!(previous, subclass);
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 3);
+        unsafe { obj.set_ivar::<u32>("_foo", 5) };
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 5);
+    }
+
+    #[test]
+    #[should_panic = "must not add instance variables"]
+    fn test_dynamic_subclass_with_ivar() {
+        ClassDecl::dynamic_subclass(test_utils::custom_class(), "WithIvar_", |decl| {
+            decl.add_ivar::<u32>("_bar");
+        });
+    }
+}
@@ -0,0 +1,286 @@
+use std::ffi::CString;
+
+use super::{checked_method_type_encoding, MethodImplementation};
+use crate::ffi;
+use crate::runtime::{Bool, Class, Imp, Protocol, Sel};
+
+/// A type for adding methods and protocols to an already registered class,
+/// similar to a category in Objective-C.
+///
+/// Unlike categories, this refuses to overwrite methods that the class
+/// already implements, unless explicitly asked to with
+/// [`ClassExtension::replace_method`]. Methods inherited from a superclass
+/// can be overridden with [`ClassExtension::add_method`] as usual.
+///
+/// # Example
+///
+/// ```no_run
+/// use objc2::declare::ClassExtension;
+/// use objc2::runtime::{Object, Sel};
+/// use objc2::{class, sel};
+///
+/// extern "C" fn is_rusty(_this: &Object, _cmd: Sel) -> bool {
+///     true
+/// }
+///
+/// let mut ext = ClassExtension::new(class!(NSObject));
+/// unsafe {
+///     ext.add_method(sel!(isRusty), is_rusty as extern "C" fn(&Object, Sel) -> bool);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ClassExtension {
+    cls: &'static Class,
+}
+
+impl ClassExtension {
+    /// Constructs a [`ClassExtension`] for adding methods to the given
+    /// class.
+    pub fn new(cls: &'static Class) -> Self {
+        Self { cls }
+    }
+
+    /// The class that is being extended.
+    pub fn class(&self) -> &'static Class {
+        self.cls
+    }
+
+    fn metaclass(&self) -> *mut ffi::objc_class {
+        self.cls.metaclass() as *const Class as *mut _
+    }
+
+    fn add_method_common(cls: *mut ffi::objc_class, sel: Sel, imp: Imp, types: CString) -> bool {
+        let success =
+            unsafe { ffi::class_addMethod(cls, sel.as_ptr() as _, Some(imp), types.as_ptr()) };
+        let success = Bool::from_raw(success).is_true();
+        if success {
+            Self::forget_verified(cls, sel);
+        }
+        success
+    }
+
+    fn replace_method_common(
+        cls: *mut ffi::objc_class,
+        sel: Sel,
+        imp: Imp,
+        types: CString,
+    ) -> Option<Imp> {
+        let old =
+            unsafe { ffi::class_replaceMethod(cls, sel.as_ptr() as _, Some(imp), types.as_ptr()) };
+        Self::forget_verified(cls, sel);
+        old
+    }
+
+    /// The method may override one with different types, so messages to the
+    /// class or its subclasses that were verified before must be verified
+    /// again.
+    #[cfg_attr(not(feature = "malloc"), allow(unused_variables))]
+    fn forget_verified(cls: *mut ffi::objc_class, sel: Sel) {
+        #[cfg(feature = "malloc")]
+        crate::message::forget_verified_method(unsafe { &*(cls as *const Class) }, sel);
+    }
+
+    /// Adds a method with the given name and implementation to the class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class already implements a method with the given name
+    /// (inherited methods don't count), or if the selector and function take
+    /// different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C, including those of any
+    /// method that is overridden on a superclass.
+    pub unsafe fn add_method<F>(&mut self, sel: Sel, func: F)
+    where
+        F: MethodImplementation,
+    {
+        let types = checked_method_type_encoding::<F>(sel);
+        let cls = self.cls as *const Class as *mut _;
+        let success = Self::add_method_common(cls, sel, func.imp(), types);
+        assert!(
+            success,
+            "Failed to add method {:?}, {} already implements it",
+            sel,
+            self.cls.name()
+        );
+    }
+
+    /// Adds a class method with the given name and implementation to the
+    /// class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class already implements a class method with the given
+    /// name (inherited methods don't count), or if the selector and function
+    /// take different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C.
+    pub unsafe fn add_class_method<F>(&mut self, sel: Sel, func: F)
+    where
+        F: MethodImplementation<Callee = Class>,
+    {
+        let types = checked_method_type_encoding::<F>(sel);
+        let success = Self::add_method_common(self.metaclass(), sel, func.imp(), types);
+        assert!(
+            success,
+            "Failed to add class method {:?}, {} already implements it",
+            sel,
+            self.cls.name()
+        );
+    }
+
+    /// Replaces the implementation of the method with the given name, or
+    /// adds it if the class doesn't implement it already.
+    ///
+    /// Returns the previous implementation, if the class implemented the
+    /// method itself.
+    ///
+    /// # Panics
+    ///
+    /// If the selector and function take different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C, including those of the
+    /// method that is replaced.
+    pub unsafe fn replace_method<F>(&mut self, sel: Sel, func: F) -> Option<Imp>
+    where
+        F: MethodImplementation,
+    {
+        let types = checked_method_type_encoding::<F>(sel);
+        let cls = self.cls as *const Class as *mut _;
+        Self::replace_method_common(cls, sel, func.imp(), types)
+    }
+
+    /// Replaces the implementation of the class method with the given name,
+    /// or adds it if the class doesn't implement it already.
+    ///
+    /// Returns the previous implementation, if the class implemented the
+    /// class method itself.
+    ///
+    /// # Panics
+    ///
+    /// If the selector and function take different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C, including those of the
+    /// method that is replaced.
+    pub unsafe fn replace_class_method<F>(&mut self, sel: Sel, func: F) -> Option<Imp>
+    where
+        F: MethodImplementation<Callee = Class>,
+    {
+        let types = checked_method_type_encoding::<F>(sel);
+        Self::replace_method_common(self.metaclass(), sel, func.imp(), types)
+    }
+
+    /// Adds the given protocol to the class.
+    ///
+    /// Returns `false` if the class already conformed to the protocol.
+    ///
+    /// Note that, unlike [`ClassDecl::register`][super::ClassDecl::register],
+    /// this does not verify that the class implements the methods required
+    /// by the protocol, so this should be done after adding the methods.
+    pub fn add_protocol(&mut self, proto: &Protocol) -> bool {
+        let cls = self.cls as *const Class as *mut _;
+        let success = unsafe { ffi::class_addProtocol(cls, proto.as_ptr()) };
+        Bool::from_raw(success).is_true()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declare::ClassDecl;
+    use crate::runtime::Object;
+    use crate::test_utils;
+
+    fn new_class(name: &str) -> &'static Class {
+        ClassDecl::new(name, test_utils::custom_class())
+            .unwrap()
+            .register()
+    }
+
+    extern "C" fn get_bar(_this: &Object, _cmd: Sel) -> u32 {
+        42
+    }
+
+    extern "C" fn get_foo(_this: &Object, _cmd: Sel) -> u32 {
+        13
+    }
+
+    extern "C" fn class_bar(_this: &Class, _cmd: Sel) -> u32 {
+        8
+    }
+
+    #[test]
+    fn test_add_method() {
+        let cls = new_class("ExtensionAddMethodObject");
+        let mut ext = ClassExtension::new(cls);
+        assert_eq!(ext.class(), cls);
+        unsafe {
+            ext.add_method(sel!(bar), get_bar as extern "C" fn(&Object, Sel) -> u32);
+            ext.add_class_method(
+                sel!(classBar),
+                class_bar as extern "C" fn(&Class, Sel) -> u32,
+            );
+            // Overriding a superclass method is fine
+            ext.add_method(sel!(foo), get_foo as extern "C" fn(&Object, Sel) -> u32);
+        }
+
+        let obj = test_utils::CustomObject::new(cls);
+        let result: u32 = unsafe { msg_send![obj, bar] };
+        assert_eq!(result, 42);
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 13);
+        let result: u32 = unsafe { msg_send![cls, classBar] };
+        assert_eq!(result, 8);
+    }
+
+    #[test]
+    #[should_panic = "already implements it"]
+    fn test_add_existing_method() {
+        let cls = new_class("ExtensionAddExistingMethodObject");
+        let mut ext = ClassExtension::new(cls);
+        unsafe {
+            ext.add_method(sel!(bar), get_bar as extern "C" fn(&Object, Sel) -> u32);
+            ext.add_method(sel!(bar), get_foo as extern "C" fn(&Object, Sel) -> u32);
+        }
+    }
+
+    #[test]
+    fn test_replace_method() {
+        let cls = new_class("ExtensionReplaceMethodObject");
+        let mut ext = ClassExtension::new(cls);
+        let old =
+            unsafe { ext.replace_method(sel!(bar), get_bar as extern "C" fn(&Object, Sel) -> u32) };
+        assert!(old.is_none());
+        let old =
+            unsafe { ext.replace_method(sel!(bar), get_foo as extern "C" fn(&Object, Sel) -> u32) };
+        assert!(old.is_some());
+
+        let obj = test_utils::CustomObject::new(cls);
+        let result: u32 = unsafe { msg_send![obj, bar] };
+        assert_eq!(result, 13);
+    }
+
+    #[test]
+    fn test_add_protocol() {
+        let cls = new_class("ExtensionAddProtocolObject");
+        let proto = test_utils::custom_subprotocol();
+        assert!(!cls.conforms_to(proto));
+
+        let mut ext = ClassExtension::new(cls);
+        assert!(ext.add_protocol(proto));
+        assert!(cls.conforms_to(proto));
+        assert!(!ext.add_protocol(proto));
+    }
+}
@@ -0,0 +1,151 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::runtime::Object;
+use crate::Encode;
+
+/// Helper trait for defining instance variables.
+///
+/// This should be implemented for an empty marker type, which can then be
+/// used within [`Ivar`] to refer to the instance variable.
+///
+/// # Safety
+///
+/// Really, [`Ivar`] should be marked as `unsafe`, but since we can't do that
+/// we'll mark this trait as `unsafe` instead. See [`Ivar`] for safety
+/// requirements.
+///
+/// # Examples
+///
+/// ```
+/// use objc2::declare::IvarType;
+///
+/// // Helper type
+/// struct MyNumber;
+///
+/// unsafe impl IvarType for MyNumber {
+///     type Type = u32;
+///     const NAME: &'static str = "_number";
+/// }
+/// ```
+pub unsafe trait IvarType {
+    /// The type of the instance variable.
+    type Type: Encode;
+    /// The name of the instance variable.
+    const NAME: &'static str;
+}
+
+/// A wrapper type over a custom instance variable.
+///
+/// This type is not meant to be constructed by itself, it must reside within
+/// another struct meant to represent an Objective-C object.
+///
+/// On [`Deref`] it then uses the [`IvarType::NAME`] string to access the ivar
+/// of the containing object.
+///
+/// Note that this is not ([currently][zst-hack]) allowed by [stacked
+/// borrows][sb], but due to [`Object`] being a zero-sized type such that we
+/// don't have provenance over the ivars anyhow, this should be just as sound
+/// as normal instance variable access.
+///
+/// [sb]: https://github.com/rust-lang/unsafe-code-guidelines/blob/e21202c60c7be03dd2ab016ada92fb5305d40438/wip/stacked-borrows.md
+/// [zst-hack]: https://github.com/rust-lang/unsafe-code-guidelines/issues/305
+///
+/// # Safety
+///
+/// This must be used within a type that act as an Objective-C object. In
+/// particular, this is never safe to have on the stack by itself.
+///
+/// Additionally, the instance variable described by `T` must be available on
+/// the specific instance, and be of the exact same type.
+///
+/// Finally, two ivars with the same name must not be used on the same
+/// object.
+#[repr(C)]
+pub struct Ivar<T: IvarType> {
+    /// Make this type allowed in `repr(C)`
+    inner: [u8; 0],
+    /// For proper variance and auto traits
+    item: PhantomData<T::Type>,
+}
+
+impl<T: IvarType> Deref for Ivar<T> {
+    type Target = T::Type;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The user ensures that this is placed in a struct that can
+        // be reinterpreted as an `Object`. Since `Ivar` can never be
+        // constructed by itself (and is neither Copy nor Clone), we know that
+        // it is guaranteed to _stay_ in said struct.
+        //
+        // Even if the user were to do `mem::swap`, the `Ivar` has a unique
+        // type (and does not hold any data), so that wouldn't break anything.
+        //
+        // Note: We technically don't have provenance over the object, nor the
+        // ivar, but the object doesn't have provenance over the ivar either,
+        // so that is fine.
+        let obj = unsafe { &*(self as *const Self as *const Object) };
+
+        // SAFETY: User ensures that the `Ivar<T>` is only used when the ivar
+        // exists and has the correct type
+        unsafe { obj.get_ivar::<T::Type>(T::NAME) }
+    }
+}
+
+impl<T: IvarType> DerefMut for Ivar<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: Same as above, and we have unique access to the ivar.
+        let obj = unsafe { &mut *(self as *mut Self as *mut Object) };
+
+        // SAFETY: Same as above.
+        unsafe { obj.get_mut_ivar::<T::Type>(T::NAME) }
+    }
+}
+
+/// Format as a pointer to the instance variable.
+impl<T: IvarType> fmt::Pointer for Ivar<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ptr: *const T::Type = &**self;
+        fmt::Pointer::fmt(&ptr, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    use super::*;
+    use crate::test_utils;
+
+    struct Foo;
+
+    unsafe impl IvarType for Foo {
+        type Type = u32;
+        const NAME: &'static str = "_foo";
+    }
+
+    #[repr(C)]
+    struct CustomObject {
+        inner: Object,
+        foo: Ivar<Foo>,
+    }
+
+    #[test]
+    fn test_zst() {
+        assert_eq!(mem::size_of::<Ivar<Foo>>(), 0);
+    }
+
+    #[test]
+    fn test_ivar_access() {
+        let mut obj = test_utils::custom_object();
+        let obj = unsafe { &mut *(&mut *obj as *mut Object as *mut CustomObject) };
+
+        assert_eq!(*obj.foo, 0);
+        *obj.foo = 42;
+        assert_eq!(*obj.foo, 42);
+        assert_eq!(unsafe { *obj.inner.get_ivar::<u32>("_foo") }, 42);
+    }
+}
@@ -0,0 +1,273 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::mem;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::{Condvar, Mutex, MutexGuard, Once};
+use std::thread::{self, ThreadId};
+
+use super::{catch_panic, ClassDecl};
+use crate::ffi;
+use crate::runtime::Class;
+
+type Provider = Box<dyn FnOnce() -> &'static Class + Send>;
+
+enum State {
+    Pending(Provider),
+    /// The provider is being called on the given thread.
+    Declaring(ThreadId),
+}
+
+// Classes that have been registered lazily, but not looked up yet. Entries
+// are removed once the class has been declared.
+static PROVIDERS: Mutex<Vec<(String, State)>> = Mutex::new(Vec::new());
+// Notified whenever a provider finishes.
+static DECLARED: Condvar = Condvar::new();
+
+fn lock() -> MutexGuard<'static, Vec<(String, State)>> {
+    PROVIDERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the entry once the provider is done, even if it panicked.
+struct Finish<'a>(&'a str);
+
+impl Drop for Finish<'_> {
+    fn drop(&mut self) {
+        lock().retain(|(name, _)| name != self.0);
+        DECLARED.notify_all();
+    }
+}
+
+/// Look up the provider for the class, and call it.
+///
+/// If another thread is already declaring the class, this waits for it to
+/// finish. If this thread is, the class is reported as not found, since the
+/// runtime does lookups of its own while the class is being declared.
+fn provide(name: &str) -> Option<&'static Class> {
+    let current = thread::current().id();
+    let mut providers = lock();
+    let mut waited = false;
+    let provider = loop {
+        let index = match providers.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            // The class was declared by another thread while we waited
+            None if waited => {
+                drop(providers);
+                return Class::get(name);
+            }
+            None => return None,
+        };
+        match &providers[index].1 {
+            State::Declaring(thread) if *thread == current => return None,
+            State::Declaring(_) => {
+                providers = DECLARED.wait(providers).unwrap_or_else(|e| e.into_inner());
+                waited = true;
+            }
+            State::Pending(_) => {
+                match mem::replace(&mut providers[index].1, State::Declaring(current)) {
+                    State::Pending(provider) => break provider,
+                    State::Declaring(_) => unreachable!(),
+                }
+            }
+        }
+    };
+    // The provider may look up other lazy classes (e.g. its superclass)
+    drop(providers);
+
+    let finish = Finish(name);
+    let cls = provider();
+    drop(finish);
+    assert_eq!(
+        cls.name(),
+        name,
+        "Lazy class provider for {} returned a different class",
+        name
+    );
+    Some(cls)
+}
+
+unsafe fn provide_raw(name: *const c_char) -> Option<&'static Class> {
+    if name.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(name) }.to_str().ok()?;
+    // The provider is user code, so we make sure that panics in it don't
+    // unwind into the runtime.
+    unsafe { catch_panic(|| provide(name)) }
+}
+
+#[cfg(apple)]
+static mut PREVIOUS_HOOK: Option<ffi::objc_hook_getClass> = None;
+
+#[cfg(apple)]
+unsafe extern "C" fn get_class_hook(
+    name: *const c_char,
+    out_cls: *mut *const ffi::objc_class,
+) -> ffi::BOOL {
+    if let Some(cls) = unsafe { provide_raw(name) } {
+        unsafe { *out_cls = cls as *const Class as *const ffi::objc_class };
+        return ffi::YES;
+    }
+    // SAFETY: Only written before the hook is installed.
+    match unsafe { PREVIOUS_HOOK } {
+        Some(previous) => unsafe { previous(name, out_cls) },
+        None => ffi::NO,
+    }
+}
+
+#[cfg(apple)]
+fn install_hook() {
+    let hook: ffi::objc_hook_getClass = get_class_hook;
+    // SAFETY: The runtime writes the old value before installing the new
+    // hook, and this is only done once.
+    unsafe {
+        ffi::objc_setHook_getClass(hook, core::ptr::addr_of_mut!(PREVIOUS_HOOK).cast());
+    }
+}
+
+#[cfg(gnustep)]
+type LookupHook = unsafe extern "C" fn(name: *const c_char) -> *const ffi::objc_class;
+
+#[cfg(gnustep)]
+static mut PREVIOUS_HOOK: Option<LookupHook> = None;
+
+#[cfg(gnustep)]
+unsafe extern "C" fn lookup_class_hook(name: *const c_char) -> *const ffi::objc_class {
+    if let Some(cls) = unsafe { provide_raw(name) } {
+        return cls as *const Class as *const ffi::objc_class;
+    }
+    // SAFETY: Only written before the hook is installed.
+    match unsafe { PREVIOUS_HOOK } {
+        Some(previous) => unsafe { previous(name) },
+        None => core::ptr::null(),
+    }
+}
+
+#[cfg(gnustep)]
+fn install_hook() {
+    // SAFETY: This is only done once. The hook is a plain global on GNUStep,
+    // so there's no way to install it atomically.
+    unsafe {
+        PREVIOUS_HOOK = ffi::_objc_lookup_class;
+        ffi::_objc_lookup_class = Some(lookup_class_hook);
+    }
+}
+
+impl ClassDecl {
+    /// Registers a closure that declares the class with the given name the
+    /// first time it is looked up, e.g. with [`Class::get`], `class!` or
+    /// `NSClassFromString`.
+    ///
+    /// The closure should declare and register the class (usually with
+    /// [`ClassDecl::new`] and [`ClassDecl::register`]), and is called at most
+    /// once. It may look up other lazily registered classes, such as its
+    /// superclass, while doing so.
+    ///
+    /// This uses the runtime's class lookup hook, which is installed the
+    /// first time this is called; lookups of classes that weren't
+    /// registered here are passed on to any previously installed hook.
+    ///
+    /// Only available on GNUStep, and on Apple platforms other than 32-bit
+    /// macOS.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a class with the given name already exists, or if a closure
+    /// for it has already been registered.
+    ///
+    /// The lookup panics if the closure returns a class with another name;
+    /// like other panics in the closure, this is handled by
+    /// [`catch_panic`][super::catch_panic].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use objc2::declare::ClassDecl;
+    /// use objc2::{class, runtime::Class};
+    ///
+    /// ClassDecl::register_lazy("MyLazyObject", || {
+    ///     let decl = ClassDecl::new("MyLazyObject", class!(NSObject)).unwrap();
+    ///     decl.register()
+    /// });
+    ///
+    /// // The class is declared here
+    /// let cls = Class::get("MyLazyObject").unwrap();
+    /// ```
+    pub fn register_lazy<F>(name: &str, provider: F)
+    where
+        F: FnOnce() -> &'static Class + Send + 'static,
+    {
+        static INSTALL: Once = Once::new();
+
+        assert!(
+            Class::get(name).is_none(),
+            "Class with name {} already exists",
+            name
+        );
+        {
+            let mut providers = lock();
+            assert!(
+                providers.iter().all(|(n, _)| n != name),
+                "Lazy class {} has already been registered",
+                name
+            );
+            providers.push((name.to_string(), State::Pending(Box::new(provider))));
+        }
+        INSTALL.call_once(install_hook);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_register_lazy() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        ClassDecl::register_lazy("LazyObject", || {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            ClassDecl::new("LazyObject", test_utils::custom_class())
+                .unwrap()
+                .register()
+        });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        let cls = Class::get("LazyObject").unwrap();
+        assert_eq!(cls.name(), "LazyObject");
+        assert_eq!(cls.superclass(), Some(test_utils::custom_class()));
+        assert_eq!(Class::get("LazyObject"), Some(cls));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        // Other lookups still fail as usual
+        assert!(Class::get("NotALazyObject").is_none());
+    }
+
+    #[test]
+    fn test_register_lazy_superclass() {
+        ClassDecl::register_lazy("LazySuperObject", || {
+            ClassDecl::new("LazySuperObject", test_utils::custom_class())
+                .unwrap()
+                .register()
+        });
+        ClassDecl::register_lazy("LazySubObject", || {
+            let superclass = Class::get("LazySuperObject").unwrap();
+            ClassDecl::new("LazySubObject", superclass)
+                .unwrap()
+                .register()
+        });
+
+        let cls = Class::get("LazySubObject").unwrap();
+        assert_eq!(cls.superclass().unwrap().name(), "LazySuperObject");
+    }
+
+    #[test]
+    #[should_panic = "already exists"]
+    fn test_register_lazy_existing() {
+        ClassDecl::register_lazy("NSObject", || unreachable!());
+    }
+}
@@ -0,0 +1,1060 @@
+/*!
+Functionality for declaring Objective-C classes.
+
+Classes can be declared using the [`ClassDecl`] struct. Instance variables and
+methods can then be added before the class is ultimately registered.
+
+Methods and protocols can also be added to classes that are already
+registered, similar to categories in Objective-C, using [`ClassExtension`].
+
+# Example
+
+The following example demonstrates declaring a class named `MyNumber` that has
+one ivar, a `u32` named `_number` and a `number` method that returns it:
+
+``` no_run
+# use objc2::{class, sel};
+# use objc2::declare::ClassDecl;
+# use objc2::runtime::{Class, Object, Sel};
+let superclass = class!(NSObject);
+let mut decl = ClassDecl::new("MyNumber", superclass).unwrap();
+
+// Add an instance variable
+decl.add_ivar::<u32>("_number");
+
+// Add an ObjC method for getting the number
+extern fn my_number_get(this: &Object, _cmd: Sel) -> u32 {
+    unsafe { *this.get_ivar("_number") }
+}
+unsafe {
+    decl.add_method(sel!(number),
+        my_number_get as extern fn(&Object, Sel) -> u32);
+}
+
+decl.register();
+```
+*/
+
+mod dynamic;
+mod extension;
+mod ivar;
+#[cfg(any(
+    gnustep,
+    all(apple, not(all(target_os = "macos", target_arch = "x86")))
+))]
+mod lazy;
+mod panic;
+mod property;
+mod resolve;
+mod verify;
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use std::ffi::CString;
+
+use crate::runtime::{Bool, Class, Imp, Object, Protocol, Sel};
+use crate::{ffi, Encode, EncodeArguments, Encoding, Message};
+
+pub use extension::ClassExtension;
+pub use ivar::{Ivar, IvarType};
+pub use panic::catch_panic;
+pub use property::{PropertyAttributes, PropertySemantics};
+pub use verify::{RegisterError, RegisterErrorKind};
+
+use resolve::Resolvers;
+use verify::AddedMethod;
+
+/// Types that can be used as the implementation of an Objective-C method.
+pub trait MethodImplementation {
+    /// The callee type of the method.
+    type Callee: Message + ?Sized;
+    /// The return type of the method.
+    type Ret: Encode;
+    /// The argument types of the method.
+    type Args: EncodeArguments;
+
+    /// Returns self as an [`Imp`] of a method.
+    fn imp(self) -> Imp;
+}
+
+macro_rules! method_decl_impl {
+    (-$s:ident, $r:ident, $f:ty, $($t:ident),*) => (
+        impl<$s, $r, $($t),*> MethodImplementation for $f
+        where
+            $s: Message + ?Sized,
+            $r: Encode,
+            $($t: Encode,)*
+        {
+            type Callee = $s;
+            type Ret = $r;
+            type Args = ($($t,)*);
+
+            fn imp(self) -> Imp {
+                unsafe { mem::transmute(self) }
+            }
+        }
+    );
+    ($($t:ident),*) => (
+        method_decl_impl!(-T, R, extern "C" fn(&T, Sel $(, $t)*) -> R, $($t),*);
+        method_decl_impl!(-T, R, extern "C" fn(&mut T, Sel $(, $t)*) -> R, $($t),*);
+    );
+}
+
+method_decl_impl!();
+method_decl_impl!(A);
+method_decl_impl!(A, B);
+method_decl_impl!(A, B, C);
+method_decl_impl!(A, B, C, D);
+method_decl_impl!(A, B, C, D, E);
+method_decl_impl!(A, B, C, D, E, F);
+method_decl_impl!(A, B, C, D, E, F, G);
+method_decl_impl!(A, B, C, D, E, F, G, H);
+method_decl_impl!(A, B, C, D, E, F, G, H, I);
+method_decl_impl!(A, B, C, D, E, F, G, H, I, J);
+method_decl_impl!(A, B, C, D, E, F, G, H, I, J, K);
+method_decl_impl!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+fn count_args(sel: Sel) -> usize {
+    sel.name().chars().filter(|&c| c == ':').count()
+}
+
+fn method_type_encoding(ret: &Encoding<'_>, args: &[Encoding<'_>]) -> CString {
+    // First two arguments are always self and the selector
+    let mut types = format!("{}{}{}", ret, <*mut Object>::ENCODING, Sel::ENCODING);
+    for enc in args {
+        use core::fmt::Write;
+        write!(&mut types, "{}", enc).unwrap();
+    }
+    CString::new(types).unwrap()
+}
+
+/// Returns the type encoding of the method implementation.
+///
+/// # Panics
+///
+/// If the selector and function take different numbers of arguments.
+fn checked_method_type_encoding<F: MethodImplementation>(sel: Sel) -> CString {
+    let encs = F::Args::ENCODINGS;
+    let sel_args = count_args(sel);
+    assert_eq!(
+        sel_args,
+        encs.len(),
+        "Selector accepts {} arguments, but function accepts {}",
+        sel_args,
+        encs.len(),
+    );
+    method_type_encoding(&F::Ret::ENCODING, encs)
+}
+
+fn log2_align_of<T>() -> u8 {
+    let align = mem::align_of::<T>();
+    // Alignments are required to be powers of 2
+    debug_assert!(align.count_ones() == 1);
+    // log2 of a power of 2 is the number of trailing zeros
+    align.trailing_zeros() as u8
+}
+
+/// A type for declaring a new class and adding new methods and ivars to it
+/// before registering it.
+pub struct ClassDecl {
+    cls: *mut Class,
+    // Used for verification when registering the class.
+    methods: Vec<AddedMethod>,
+    protocols: Vec<&'static Protocol>,
+    resolvers: Resolvers,
+}
+
+// SAFETY: The stuff that touch global state does so using locks internally.
+//
+// Modifying the class itself can only be done through `&mut`, so Sync is
+// safe (e.g. we can't accidentally call `add_ivar` at the same time from two
+// different threads).
+//
+// (Though actually, that would be safe since the entire runtime is locked
+// when doing so...).
+//
+// Finally, there are no requirements that the class must be registered on the
+// same thread that allocated it.
+unsafe impl Send for ClassDecl {}
+unsafe impl Sync for ClassDecl {}
+
+impl ClassDecl {
+    fn with_superclass(name: &str, superclass: Option<&Class>) -> Option<ClassDecl> {
+        let name = CString::new(name).unwrap();
+        let super_ptr = superclass.map_or(ptr::null(), |c| c) as _;
+        let cls = unsafe { ffi::objc_allocateClassPair(super_ptr, name.as_ptr(), 0) };
+        if cls.is_null() {
+            None
+        } else {
+            Some(ClassDecl {
+                cls: cls as _,
+                methods: Vec::new(),
+                protocols: Vec::new(),
+                resolvers: Resolvers::default(),
+            })
+        }
+    }
+
+    /// Constructs a [`ClassDecl`] with the given name and superclass.
+    ///
+    /// Returns [`None`] if the class couldn't be allocated, or a class with
+    /// that name already exist.
+    pub fn new(name: &str, superclass: &Class) -> Option<ClassDecl> {
+        ClassDecl::with_superclass(name, Some(superclass))
+    }
+
+    /// Constructs a [`ClassDecl`] declaring a new root class with the given
+    /// name.
+    ///
+    /// Returns [`None`] if the class couldn't be allocated.
+    ///
+    /// An implementation for `+initialize` must also be given; the runtime
+    /// calls this method for all classes, so it must be defined on root
+    /// classes.
+    ///
+    /// Note that implementing a root class is not a simple endeavor!
+    /// For example, your class probably cannot be passed to Cocoa code unless
+    /// the entire `NSObject` protocol is implemented.
+    /// Functionality it expects, like implementations of `-retain` and
+    /// `-release` used by ARC, will not be present otherwise.
+    pub fn root(name: &str, intitialize_fn: extern "C" fn(&Class, Sel)) -> Option<ClassDecl> {
+        let mut decl = ClassDecl::with_superclass(name, None);
+        if let Some(ref mut decl) = decl {
+            unsafe {
+                decl.add_class_method(sel!(initialize), intitialize_fn);
+            }
+        }
+        decl
+    }
+
+    /// Adds a method with the given name and implementation.
+    ///
+    /// Note that the implementation must not panic, as unwinding into
+    /// Objective-C is undefined behaviour; use [`catch_panic`] in its body
+    /// if that may happen.
+    ///
+    /// The receiver of the method may be any type that implements
+    /// [`Message`], which allows using the Rust type that represents the
+    /// class directly (e.g. `extern "C" fn(&MyObject, Sel)`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method wasn't sucessfully added or if the selector and
+    /// function take different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C.
+    pub unsafe fn add_method<T, F>(&mut self, sel: Sel, func: F)
+    where
+        T: Message + ?Sized,
+        F: MethodImplementation<Callee = T>,
+    {
+        let encs = F::Args::ENCODINGS;
+        let types = checked_method_type_encoding::<F>(sel);
+        let success = Bool::from_raw(unsafe {
+            ffi::class_addMethod(
+                self.cls as _,
+                sel.as_ptr() as _,
+                Some(func.imp()),
+                types.as_ptr(),
+            )
+        });
+        assert!(success.is_true(), "Failed to add method {:?}", sel);
+        self.methods.push(AddedMethod {
+            sel,
+            is_instance_method: true,
+            ret: F::Ret::ENCODING,
+            args: encs,
+        });
+    }
+
+    /// Adds a class method with the given name and implementation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method wasn't sucessfully added or if the selector and
+    /// function take different numbers of arguments.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C.
+    pub unsafe fn add_class_method<F>(&mut self, sel: Sel, func: F)
+    where
+        F: MethodImplementation<Callee = Class>,
+    {
+        let encs = F::Args::ENCODINGS;
+        let types = checked_method_type_encoding::<F>(sel);
+        let metaclass = unsafe { &*self.cls }.metaclass() as *const _ as *mut _;
+        let success = Bool::from_raw(unsafe {
+            ffi::class_addMethod(
+                metaclass,
+                sel.as_ptr() as _,
+                Some(func.imp()),
+                types.as_ptr(),
+            )
+        });
+        assert!(success.is_true(), "Failed to add class method {:?}", sel);
+        self.methods.push(AddedMethod {
+            sel,
+            is_instance_method: false,
+            ret: F::Ret::ENCODING,
+            args: encs,
+        });
+    }
+
+    /// Adds an ivar with type `T` and the provided name.
+    ///
+    /// # Panics
+    ///
+    /// If the ivar wasn't successfully added.
+    pub fn add_ivar<T: Encode>(&mut self, name: &str) {
+        let c_name = CString::new(name).unwrap();
+        let encoding = CString::new(T::ENCODING.to_string()).unwrap();
+        let size = mem::size_of::<T>();
+        let align = log2_align_of::<T>();
+        let success = Bool::from_raw(unsafe {
+            ffi::class_addIvar(
+                self.cls as _,
+                c_name.as_ptr(),
+                size,
+                align,
+                encoding.as_ptr(),
+            )
+        });
+        assert!(success.is_true(), "Failed to add ivar {}", name);
+    }
+
+    /// Adds the given protocol to self.
+    ///
+    /// # Panics
+    ///
+    /// If the protocol wasn't successfully added.
+    pub fn add_protocol(&mut self, proto: &Protocol) {
+        let success = unsafe { ffi::class_addProtocol(self.cls as _, proto.as_ptr()) };
+        let success = Bool::from_raw(success).is_true();
+        assert!(success, "Failed to add protocol {:?}", proto);
+        // SAFETY: Protocols are never deallocated.
+        let proto: &'static Protocol = unsafe { &*(proto as *const Protocol) };
+        self.protocols.push(proto);
+    }
+
+    /// Adds a declared property with type `T`, the given name and
+    /// attributes.
+    ///
+    /// Note that this only declares the property; the accessor methods must
+    /// be added separately (or use [`ClassDecl::add_synthesized_property`]).
+    ///
+    /// # Panics
+    ///
+    /// If the property wasn't successfully added (e.g. if a property with
+    /// that name is already declared on the class or a superclass).
+    pub fn add_property<T: Encode>(&mut self, name: &str, attributes: PropertyAttributes<'_>) {
+        let c_name = CString::new(name).unwrap();
+        let raw = attributes.to_raw(&T::ENCODING);
+        let success = Bool::from_raw(unsafe {
+            ffi::class_addProperty(self.cls as _, c_name.as_ptr(), raw.as_ptr(), raw.len() as _)
+        });
+        assert!(success.is_true(), "Failed to add property {}", name);
+    }
+
+    /// Replaces the attributes of the property with the given name, or adds
+    /// the property if it doesn't exist.
+    pub fn replace_property<T: Encode>(&mut self, name: &str, attributes: PropertyAttributes<'_>) {
+        let c_name = CString::new(name).unwrap();
+        let raw = attributes.to_raw(&T::ENCODING);
+        unsafe {
+            ffi::class_replaceProperty(
+                self.cls as _,
+                c_name.as_ptr(),
+                raw.as_ptr(),
+                raw.len() as _,
+            );
+        }
+    }
+
+    /// Adds a declared property backed by the instance variable described
+    /// by `I`, along with synthesized getter (and, unless the property is
+    /// `readonly`, setter) methods.
+    ///
+    /// The instance variable must already have been added with
+    /// [`ClassDecl::add_ivar`]. The [`PropertyAttributes::ivar`] field is
+    /// ignored, [`IvarType::NAME`] is used instead.
+    ///
+    /// The synthesized accessors simply read and write the instance variable,
+    /// and so only the [`PropertySemantics::Assign`] semantics are supported.
+    ///
+    /// # Panics
+    ///
+    /// If the instance variable doesn't exist or has a different type, if
+    /// the semantics are not [`PropertySemantics::Assign`], or if the
+    /// property or methods weren't successfully added.
+    pub fn add_synthesized_property<I>(&mut self, name: &str, attributes: PropertyAttributes<'_>)
+    where
+        I: IvarType,
+        I::Type: Copy,
+    {
+        let cls = unsafe { &*self.cls };
+        match cls.instance_variable(I::NAME) {
+            Some(ivar) => assert!(
+                I::Type::ENCODING.equivalent_to_str(ivar.type_encoding()),
+                "Ivar {} has type {}, expected {}",
+                I::NAME,
+                ivar.type_encoding(),
+                I::Type::ENCODING,
+            ),
+            None => panic!("Ivar {} not found on class {:?}", I::NAME, cls),
+        }
+        assert_eq!(
+            attributes.semantics,
+            PropertySemantics::Assign,
+            "Synthesized properties only support assign semantics",
+        );
+
+        extern "C" fn getter<I: IvarType>(this: &Object, _cmd: Sel) -> I::Type
+        where
+            I::Type: Copy,
+        {
+            // SAFETY: The ivar was verified to exist and have the correct
+            // type when the method was added.
+            unsafe { *this.get_ivar::<I::Type>(I::NAME) }
+        }
+
+        extern "C" fn setter<I: IvarType>(this: &mut Object, _cmd: Sel, value: I::Type)
+        where
+            I::Type: Copy,
+        {
+            // SAFETY: Same as above.
+            unsafe { this.set_ivar::<I::Type>(I::NAME, value) }
+        }
+
+        let getter_sel = attributes
+            .getter
+            .unwrap_or_else(|| PropertyAttributes::default_getter(name));
+        // SAFETY: The types of the accessors match the ivar's type.
+        unsafe {
+            let getter: extern "C" fn(&Object, Sel) -> I::Type = getter::<I>;
+            self.add_method(getter_sel, getter);
+        }
+        if !attributes.readonly {
+            let setter_sel = attributes
+                .setter
+                .unwrap_or_else(|| PropertyAttributes::default_setter(name));
+            // SAFETY: Same as above.
+            unsafe {
+                let setter: extern "C" fn(&mut Object, Sel, I::Type) = setter::<I>;
+                self.add_method(setter_sel, setter);
+            }
+        }
+
+        let attributes = PropertyAttributes {
+            ivar: Some(I::NAME),
+            ..attributes
+        };
+        self.add_property::<I::Type>(name, attributes);
+    }
+
+    /// Sets a closure that is used to implement `+resolveInstanceMethod:`.
+    ///
+    /// The closure is called by the runtime the first time a selector that
+    /// no instance method exists for is sent to an instance of the class (or
+    /// of one of its subclasses). It may then add the method using the given
+    /// [`ClassExtension`], and should return `true` if it did so, which makes
+    /// the runtime retry the message send.
+    ///
+    /// Calling this again replaces the previous closure.
+    pub fn set_instance_method_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&mut ClassExtension, Sel) -> bool + Send + Sync + 'static,
+    {
+        if self.resolvers.instance_method.is_none() {
+            let imp: extern "C" fn(&Class, Sel, Sel) -> Bool = resolve::resolve_instance_method;
+            // SAFETY: The signature matches `+resolveInstanceMethod:`
+            unsafe { self.add_class_method(sel!(resolveInstanceMethod:), imp) };
+        }
+        self.resolvers.instance_method = Some(Arc::new(resolver));
+    }
+
+    /// Sets a closure that is used to implement `+resolveClassMethod:`.
+    ///
+    /// This works like [`ClassDecl::set_instance_method_resolver`], except
+    /// that it is called for unknown selectors sent to the class itself, and
+    /// the closure should thus add a class method.
+    ///
+    /// Calling this again replaces the previous closure.
+    pub fn set_class_method_resolver<F>(&mut self, resolver: F)
+    where
+        F: Fn(&mut ClassExtension, Sel) -> bool + Send + Sync + 'static,
+    {
+        if self.resolvers.class_method.is_none() {
+            let imp: extern "C" fn(&Class, Sel, Sel) -> Bool = resolve::resolve_class_method;
+            // SAFETY: The signature matches `+resolveClassMethod:`
+            unsafe { self.add_class_method(sel!(resolveClassMethod:), imp) };
+        }
+        self.resolvers.class_method = Some(Arc::new(resolver));
+    }
+
+    /// Sets a closure that is used to implement
+    /// `-forwardingTargetForSelector:`.
+    ///
+    /// The closure is called with the receiver and the unknown selector
+    /// (after method resolution failed), and may return another object that
+    /// the message should be sent to instead, or null to continue with the
+    /// normal forwarding machinery.
+    ///
+    /// Note that it is the Foundation framework that calls this method, so
+    /// it is only used when that is linked.
+    ///
+    /// Calling this again replaces the previous closure.
+    pub fn set_forwarding_target<F>(&mut self, target: F)
+    where
+        F: Fn(&Object, Sel) -> *mut Object + Send + Sync + 'static,
+    {
+        if self.resolvers.forwarding_target.is_none() {
+            let imp: extern "C" fn(&Object, Sel, Sel) -> *mut Object = resolve::forwarding_target;
+            // SAFETY: The signature matches `-forwardingTargetForSelector:`
+            unsafe { self.add_method(sel!(forwardingTargetForSelector:), imp) };
+        }
+        self.resolvers.forwarding_target = Some(Arc::new(target));
+    }
+
+    /// Registers the [`ClassDecl`], consuming it, and returns a reference to
+    /// the newly registered [`Class`].
+    ///
+    /// # Panics
+    ///
+    /// If the class fails verification, see [`ClassDecl::try_register`].
+    pub fn register(self) -> &'static Class {
+        match self.try_register() {
+            Ok(cls) => cls,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Verifies and registers the [`ClassDecl`], consuming it, and returns a
+    /// reference to the newly registered [`Class`].
+    ///
+    /// Verification checks that the type encoding of each added method
+    /// matches that of the method it overrides on the superclass, and that
+    /// of the method description in any adopted protocol.
+    ///
+    /// It is also checked that the class (or one of its superclasses)
+    /// implements every method required by the adopted protocols and the
+    /// protocols they inherit from.
+    ///
+    /// Verification is always done when debug assertions are enabled, and
+    /// can be enabled in release mode with the `"verify_declare"` feature.
+    /// Otherwise this always succeeds.
+    ///
+    /// # Errors
+    ///
+    /// If verification failed, a [`RegisterError`] describing each problem
+    /// is returned, and the class is disposed of without being registered.
+    pub fn try_register(self) -> Result<&'static Class, RegisterError> {
+        if cfg!(any(debug_assertions, feature = "verify_declare")) {
+            verify::verify_class(unsafe { &*self.cls }, &self.methods, &self.protocols)?;
+        }
+
+        // Forget self, otherwise the class will be disposed in drop
+        let mut this = ManuallyDrop::new(self);
+        let cls = this.cls;
+        // Drop the remaining fields manually
+        drop(mem::take(&mut this.methods));
+        drop(mem::take(&mut this.protocols));
+        let resolvers = mem::take(&mut this.resolvers);
+
+        unsafe { ffi::objc_registerClassPair(cls as _) };
+        let cls: &'static Class = unsafe { &*cls };
+        if !resolvers.is_empty() {
+            resolve::register(cls, resolvers);
+        }
+        Ok(cls)
+    }
+
+    /// Creates a copy of the registered class under a new name, and
+    /// registers it.
+    ///
+    /// The copy has the same superclass, methods, ivars and protocols as the
+    /// original, but method changes made to one of them afterwards don't
+    /// affect the other.
+    ///
+    /// Returns [`None`] if the class couldn't be duplicated, or a class with
+    /// the given name already exists.
+    ///
+    /// This is only available on Apple platforms.
+    #[cfg(apple)]
+    pub fn duplicate(cls: &Class, name: &str) -> Option<&'static Class> {
+        if Class::get(name).is_some() {
+            return None;
+        }
+        let c_name = CString::new(name).unwrap();
+        let duplicate = unsafe { ffi::objc_duplicateClass(cls.as_ptr(), c_name.as_ptr(), 0) };
+        if duplicate.is_null() {
+            None
+        } else {
+            let duplicate: &'static Class = unsafe { &*(duplicate as *const Class) };
+            resolve::duplicate(cls, duplicate);
+            Some(duplicate)
+        }
+    }
+
+    /// Disposes of a registered class and its metaclass, removing it from
+    /// the runtime so that a class with the same name can be declared again.
+    ///
+    /// # Panics
+    ///
+    /// If the class is a metaclass, or if it has any registered subclasses
+    /// (those must be disposed first).
+    ///
+    /// # Safety
+    ///
+    /// The class must have been created with [`ClassDecl`] (or
+    /// [`ClassDecl::duplicate`]), and no instances of it may exist.
+    ///
+    /// The class must not be used afterwards, which includes all references
+    /// to it (even though they are `'static`), as well as any [`class!`]
+    /// invocations that may have cached it.
+    ///
+    /// [`class!`]: crate::class
+    pub unsafe fn dispose(cls: &'static Class) {
+        let is_metaclass = unsafe { ffi::class_isMetaClass(cls.as_ptr()) };
+        assert!(
+            !Bool::from_raw(is_metaclass).is_true(),
+            "Cannot dispose of metaclass {:?}",
+            cls
+        );
+        if let Some(subclass) = cls.subclasses().first() {
+            panic!(
+                "Cannot dispose of class {:?}, subclass {:?} still exists",
+                cls, subclass
+            );
+        }
+        resolve::unregister(cls);
+        #[cfg(feature = "malloc")]
+        crate::message::forget_verified(cls);
+        unsafe { ffi::objc_disposeClassPair(cls.as_ptr() as *mut _) }
+    }
+}
+
+impl Drop for ClassDecl {
+    fn drop(&mut self) {
+        unsafe { ffi::objc_disposeClassPair(self.cls as _) }
+    }
+}
+
+/// A type for declaring a new protocol and adding new methods to it
+/// before registering it.
+pub struct ProtocolDecl {
+    proto: *mut Protocol,
+}
+
+// SAFETY: Similar to ClassDecl
+unsafe impl Send for ProtocolDecl {}
+unsafe impl Sync for ProtocolDecl {}
+
+impl ProtocolDecl {
+    /// Constructs a [`ProtocolDecl`] with the given name.
+    ///
+    /// Returns [`None`] if the protocol couldn't be allocated.
+    pub fn new(name: &str) -> Option<ProtocolDecl> {
+        let c_name = CString::new(name).unwrap();
+        let proto = unsafe { ffi::objc_allocateProtocol(c_name.as_ptr()) } as *mut Protocol;
+        if proto.is_null() {
+            None
+        } else {
+            Some(ProtocolDecl { proto })
+        }
+    }
+
+    fn add_method_description_common<Args, Ret>(
+        &mut self,
+        sel: Sel,
+        is_required: bool,
+        is_instance_method: bool,
+    ) where
+        Args: EncodeArguments,
+        Ret: Encode,
+    {
+        let encs = Args::ENCODINGS;
+        let sel_args = count_args(sel);
+        assert_eq!(
+            sel_args,
+            encs.len(),
+            "Selector accepts {} arguments, but function accepts {}",
+            sel_args,
+            encs.len(),
+        );
+        let types = method_type_encoding(&Ret::ENCODING, encs);
+        unsafe {
+            ffi::protocol_addMethodDescription(
+                self.proto as _,
+                sel.as_ptr() as _,
+                types.as_ptr(),
+                Bool::new(is_required).as_raw(),
+                Bool::new(is_instance_method).as_raw(),
+            );
+        }
+    }
+
+    /// Adds an instance method declaration with a given description.
+    pub fn add_method_description<Args, Ret>(&mut self, sel: Sel, is_required: bool)
+    where
+        Args: EncodeArguments,
+        Ret: Encode,
+    {
+        self.add_method_description_common::<Args, Ret>(sel, is_required, true)
+    }
+
+    /// Adds a class method declaration with a given description.
+    pub fn add_class_method_description<Args, Ret>(&mut self, sel: Sel, is_required: bool)
+    where
+        Args: EncodeArguments,
+        Ret: Encode,
+    {
+        self.add_method_description_common::<Args, Ret>(sel, is_required, false)
+    }
+
+    fn add_property_common<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: PropertyAttributes<'_>,
+        is_required: bool,
+        is_instance_property: bool,
+    ) {
+        let c_name = CString::new(name).unwrap();
+        let raw = attributes.to_raw(&T::ENCODING);
+        unsafe {
+            ffi::protocol_addProperty(
+                self.proto as _,
+                c_name.as_ptr(),
+                raw.as_ptr(),
+                raw.len() as _,
+                Bool::new(is_required).as_raw(),
+                Bool::new(is_instance_property).as_raw(),
+            );
+        }
+    }
+
+    /// Adds an instance property declaration with type `T`, the given name
+    /// and attributes.
+    pub fn add_property<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: PropertyAttributes<'_>,
+        is_required: bool,
+    ) {
+        self.add_property_common::<T>(name, attributes, is_required, true)
+    }
+
+    /// Adds a class property declaration with type `T`, the given name and
+    /// attributes.
+    pub fn add_class_property<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: PropertyAttributes<'_>,
+        is_required: bool,
+    ) {
+        self.add_property_common::<T>(name, attributes, is_required, false)
+    }
+
+    /// Adds a requirement on another protocol.
+    pub fn add_protocol(&mut self, proto: &Protocol) {
+        unsafe {
+            ffi::protocol_addProtocol(self.proto as _, proto.as_ptr());
+        }
+    }
+
+    /// Registers the [`ProtocolDecl`], consuming it and returning a reference
+    /// to the newly registered [`Protocol`].
+    pub fn register(self) -> &'static Protocol {
+        unsafe {
+            ffi::objc_registerProtocol(self.proto as _);
+            &*self.proto
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_custom_class() {
+        // Registering the custom class is in test_utils
+        let obj = test_utils::custom_object();
+        let _: () = unsafe { msg_send![obj, setFoo: 13u32] };
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 13);
+    }
+
+    #[test]
+    fn test_class_method() {
+        let cls = test_utils::custom_class();
+        let result: u32 = unsafe { msg_send![cls, classFoo] };
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn test_property() {
+        let cls = test_utils::custom_class();
+        let property = cls.property("foo").unwrap();
+        assert_eq!(property.name(), "foo");
+        #[cfg(feature = "malloc")]
+        {
+            assert!(<u32>::ENCODING.equivalent_to_str(&property.attribute_value("T").unwrap()));
+            assert_eq!(&*property.attribute_value("N").unwrap(), "");
+            assert_eq!(&*property.attribute_value("V").unwrap(), "_foo");
+            assert!(property.attribute_value("R").is_none());
+        }
+    }
+
+    #[test]
+    fn test_synthesized_property() {
+        struct Number;
+
+        unsafe impl IvarType for Number {
+            type Type = u32;
+            const NAME: &'static str = "_number";
+        }
+
+        let mut decl =
+            ClassDecl::new("SynthesizedPropertyObject", test_utils::custom_class()).unwrap();
+        decl.add_ivar::<u32>("_number");
+        decl.add_synthesized_property::<Number>("number", Default::default());
+        let cls = decl.register();
+
+        assert!(cls.property("number").is_some());
+        assert!(cls.instance_method(sel!(number)).is_some());
+        assert!(cls.instance_method(sel!(setNumber:)).is_some());
+
+        let obj = test_utils::CustomObject::new(cls);
+        let _: () = unsafe { msg_send![obj, setNumber: 5u32] };
+        let result: u32 = unsafe { msg_send![obj, number] };
+        assert_eq!(result, 5);
+    }
+
+    #[test]
+    fn test_instance_method_resolver() {
+        extern "C" fn dynamic_foo(_this: &Object, _cmd: Sel) -> u32 {
+            21
+        }
+
+        let mut decl =
+            ClassDecl::new("InstanceMethodResolverObject", test_utils::custom_class()).unwrap();
+        decl.set_instance_method_resolver(|ext, sel| {
+            if sel == sel!(dynamicFoo) {
+                let imp: extern "C" fn(&Object, Sel) -> u32 = dynamic_foo;
+                unsafe { ext.add_method(sel, imp) };
+                true
+            } else {
+                false
+            }
+        });
+        let cls = decl.register();
+
+        // Also works on subclasses
+        let subclass = ClassDecl::new("InstanceMethodResolverSubclass", cls)
+            .unwrap()
+            .register();
+
+        for cls in [cls, subclass] {
+            let obj = test_utils::CustomObject::new(cls);
+            let result: u32 = unsafe { msg_send![obj, dynamicFoo] };
+            assert_eq!(result, 21);
+            assert!(cls.instance_method(sel!(dynamicFoo)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_class_method_resolver() {
+        extern "C" fn dynamic_class_foo(_this: &Class, _cmd: Sel) -> u32 {
+            22
+        }
+
+        let mut decl =
+            ClassDecl::new("ClassMethodResolverObject", test_utils::custom_class()).unwrap();
+        decl.set_class_method_resolver(|ext, sel| {
+            if sel == sel!(dynamicClassFoo) {
+                let imp: extern "C" fn(&Class, Sel) -> u32 = dynamic_class_foo;
+                unsafe { ext.add_class_method(sel, imp) };
+                true
+            } else {
+                false
+            }
+        });
+        let cls = decl.register();
+
+        let result: u32 = unsafe { msg_send![cls, dynamicClassFoo] };
+        assert_eq!(result, 22);
+    }
+
+    #[test]
+    fn test_forwarding_target() {
+        let target = test_utils::custom_object();
+        let target_ptr = &*target as *const Object as usize;
+
+        let mut decl =
+            ClassDecl::new("ForwardingTargetObject", test_utils::custom_class()).unwrap();
+        decl.set_forwarding_target(move |_this, sel| {
+            if sel == sel!(bar) {
+                target_ptr as *mut Object
+            } else {
+                ptr::null_mut()
+            }
+        });
+        let cls = decl.register();
+
+        let obj = test_utils::CustomObject::new(cls);
+        let result: *mut Object = unsafe { msg_send![obj, forwardingTargetForSelector: sel!(bar)] };
+        assert_eq!(result as usize, target_ptr);
+        let result: *mut Object = unsafe { msg_send![obj, forwardingTargetForSelector: sel!(baz)] };
+        assert!(result.is_null());
+    }
+
+    #[test]
+    fn test_dispose() {
+        let name = "DisposableObject";
+        for _ in 0..2 {
+            let cls = ClassDecl::new(name, test_utils::custom_class())
+                .unwrap()
+                .register();
+            assert_eq!(Class::get(name), Some(cls));
+            unsafe { ClassDecl::dispose(cls) };
+            assert_eq!(Class::get(name), None);
+        }
+    }
+
+    #[test]
+    #[should_panic = "still exists"]
+    fn test_dispose_with_subclass() {
+        let cls = ClassDecl::new("DisposableSuperclass", test_utils::custom_class())
+            .unwrap()
+            .register();
+        let _subclass = ClassDecl::new("DisposableSubclass", cls)
+            .unwrap()
+            .register();
+        unsafe { ClassDecl::dispose(cls) };
+    }
+
+    #[test]
+    #[cfg(apple)]
+    fn test_duplicate() {
+        let cls = ClassDecl::new("DuplicatedObject", test_utils::custom_class())
+            .unwrap()
+            .register();
+        let duplicate = ClassDecl::duplicate(cls, "DuplicateObject").unwrap();
+        assert_eq!(duplicate.name(), "DuplicateObject");
+        assert_eq!(duplicate.superclass(), cls.superclass());
+        assert!(ClassDecl::duplicate(cls, "DuplicateObject").is_none());
+
+        let obj = test_utils::CustomObject::new(duplicate);
+        let _: () = unsafe { msg_send![obj, setFoo: 3u32] };
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "verify_declare"))]
+    fn test_verify_override() {
+        let mut decl = ClassDecl::new("VerifyOverrideObject", test_utils::custom_class()).unwrap();
+
+        // `foo` returns `u32` on the superclass
+        extern "C" fn wrong_foo(_this: &Object, _cmd: Sel) -> u64 {
+            0
+        }
+        unsafe {
+            decl.add_method(sel!(foo), wrong_foo as extern "C" fn(&Object, Sel) -> u64);
+        }
+
+        let err = decl.try_register().unwrap_err();
+        assert_eq!(err.class_name(), "VerifyOverrideObject");
+        assert_eq!(err.errors().len(), 1);
+        match &err.errors()[0] {
+            RegisterErrorKind::MismatchedOverride {
+                sel,
+                is_instance_method,
+                ..
+            } => {
+                assert_eq!(*sel, sel!(foo));
+                assert!(is_instance_method);
+            }
+            err => panic!("unexpected error {}", err),
+        }
+
+        // The class was disposed, so the name can be reused.
+        let decl = ClassDecl::new("VerifyOverrideObject", test_utils::custom_class()).unwrap();
+        decl.register();
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "verify_declare"))]
+    fn test_verify_protocol_method() {
+        let mut decl = ClassDecl::new("VerifyProtocolObject", test_utils::custom_class()).unwrap();
+        decl.add_protocol(test_utils::custom_verify_protocol());
+
+        // `calculateFoo:` takes and returns `u32` in the protocol
+        extern "C" fn wrong_calculate_foo(_this: &Object, _cmd: Sel, _foo: u8) -> u32 {
+            0
+        }
+        unsafe {
+            decl.add_method(
+                sel!(calculateFoo:),
+                wrong_calculate_foo as extern "C" fn(&Object, Sel, u8) -> u32,
+            );
+        }
+
+        let err = decl.try_register().unwrap_err();
+        assert!(matches!(
+            err.errors(),
+            [RegisterErrorKind::MismatchedProtocolMethod { .. }]
+        ));
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "verify_declare"))]
+    fn test_verify_conforming_class() {
+        let mut decl =
+            ClassDecl::new("VerifyConformingObject", test_utils::custom_class()).unwrap();
+        decl.add_protocol(test_utils::custom_verify_protocol());
+
+        extern "C" fn calculate_foo(_this: &Object, _cmd: Sel, foo: u32) -> u32 {
+            foo + 1
+        }
+        unsafe {
+            decl.add_method(
+                sel!(calculateFoo:),
+                calculate_foo as extern "C" fn(&Object, Sel, u32) -> u32,
+            );
+        }
+
+        let cls = decl.try_register().unwrap();
+        assert!(cls.conforms_to(test_utils::custom_verify_protocol()));
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "verify_declare"))]
+    fn test_verify_missing_protocol_method() {
+        let mut decl =
+            ClassDecl::new("VerifyMissingProtocolObject", test_utils::custom_class()).unwrap();
+        // `foo` is implemented by the superclass, but `calculateFoo:` is not.
+        decl.add_protocol(test_utils::custom_verify_protocol());
+
+        let err = decl.try_register().unwrap_err();
+        assert_eq!(
+            err.errors(),
+            [RegisterErrorKind::MissingProtocolMethod {
+                protocol: test_utils::custom_verify_protocol(),
+                sel: sel!(calculateFoo:),
+                is_instance_method: true,
+            }]
+        );
+    }
+}
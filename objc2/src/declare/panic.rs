@@ -0,0 +1,185 @@
+use alloc::string::String;
+use core::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Runs the given closure, and makes sure that a panic inside it doesn't
+/// unwind into Objective-C.
+///
+/// This is meant to be used as the body of method implementations added
+/// with e.g. [`ClassDecl::add_method`][super::ClassDecl::add_method], since
+/// unwinding out of an `extern "C" fn` (and through the Objective-C frames
+/// that called it) is undefined behaviour.
+///
+/// If the closure panics, and the `exception` feature is enabled, the panic
+/// is converted into an `NSException` with the name `RustPanic` and the panic
+/// message as its reason, which is then thrown with
+/// [`exception::throw`][crate::exception::throw]. Otherwise (or if
+/// `NSException` isn't available), the process is aborted.
+///
+/// Note that the closure is assumed to be unwind safe; since the panic never
+/// resumes in Rust, broken invariants can only be observed by Objective-C
+/// code that catches the exception.
+///
+/// # Safety
+///
+/// When the `exception` feature is enabled, the exception unwinds through
+/// the caller of this function, so this must only be called directly in the
+/// body of a method implementation called by the Objective-C runtime. See
+/// also the safety section of [`exception::throw`][crate::exception::throw].
+///
+/// # Example
+///
+/// ```no_run
+/// use objc2::declare::catch_panic;
+/// use objc2::runtime::{Object, Sel};
+///
+/// extern "C" fn number(_this: &Object, _cmd: Sel) -> u32 {
+///     let body = || {
+///         assert!(true, "this would otherwise be undefined behaviour");
+///         42
+///     };
+///     unsafe { catch_panic(body) }
+/// }
+/// ```
+pub unsafe fn catch_panic<R>(f: impl FnOnce() -> R) -> R {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => unsafe { handle_panic(&*payload) },
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&'static str>() {
+        (*s).into()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Box<dyn Any>".into()
+    }
+}
+
+#[cfg(feature = "exception")]
+unsafe fn handle_panic(payload: &(dyn Any + Send)) -> ! {
+    use core::ptr::{self, NonNull};
+
+    use crate::rc::{Id, Shared};
+    use crate::runtime::{Class, Object};
+
+    unsafe fn new_string(cls: &Class, s: &str) -> Id<Object, Shared> {
+        // NSUTF8StringEncoding
+        const UTF8_ENCODING: usize = 4;
+        let bytes = s.as_ptr() as *const core::ffi::c_void;
+        unsafe {
+            let obj: *mut Object = msg_send![cls, alloc];
+            let obj: *mut Object = msg_send![
+                obj,
+                initWithBytes: bytes,
+                length: s.len(),
+                encoding: UTF8_ENCODING,
+            ];
+            Id::new(NonNull::new(obj).unwrap())
+        }
+    }
+
+    let message = panic_message(payload);
+    if let (Some(string_cls), Some(exception_cls)) =
+        (Class::get("NSString"), Class::get("NSException"))
+    {
+        let exception = unsafe {
+            let name = new_string(string_cls, "RustPanic");
+            let reason = new_string(string_cls, &message);
+            let user_info: *mut Object = ptr::null_mut();
+            let exception: *mut Object = msg_send![
+                exception_cls,
+                exceptionWithName: &*name,
+                reason: &*reason,
+                userInfo: user_info,
+            ];
+            NonNull::new(exception).map(|e| Id::<Object, Shared>::retain(e))
+        };
+        if let Some(exception) = exception {
+            unsafe { crate::exception::throw(Some(&exception)) }
+        }
+    }
+    abort(&message)
+}
+
+#[cfg(not(feature = "exception"))]
+unsafe fn handle_panic(payload: &(dyn Any + Send)) -> ! {
+    abort(&panic_message(payload))
+}
+
+fn abort(message: &str) -> ! {
+    std::eprintln!(
+        "panicked in Objective-C method, aborting to avoid unwinding into Objective-C: {}",
+        message
+    );
+    std::process::abort()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+
+    #[test]
+    fn test_no_panic() {
+        let value = unsafe { catch_panic(|| 5) };
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload: Box<dyn Any + Send> = Box::new("static message");
+        assert_eq!(panic_message(&*payload), "static message");
+        let payload: Box<dyn Any + Send> = Box::new(String::from("owned message"));
+        assert_eq!(panic_message(&*payload), "owned message");
+        let payload: Box<dyn Any + Send> = Box::new(5);
+        assert_eq!(panic_message(&*payload), "Box<dyn Any>");
+    }
+
+    #[test]
+    #[cfg(feature = "exception")]
+    fn test_panic_to_exception() {
+        use std::ffi::CStr;
+        use std::os::raw::c_char;
+
+        use crate::declare::ClassDecl;
+        use crate::exception::catch;
+        use crate::runtime::{Class, Object, Sel};
+
+        // `NSException` and `NSString` are only available with Foundation.
+        #[cfg_attr(apple, link(name = "Foundation", kind = "framework"))]
+        extern "C" {}
+
+        fn to_str(string: *mut Object) -> &'static str {
+            let utf8: *const c_char = unsafe { msg_send![string, UTF8String] };
+            unsafe { CStr::from_ptr(utf8) }.to_str().unwrap()
+        }
+
+        extern "C" fn panicking(_this: &Class, _cmd: Sel, number: u32) -> u32 {
+            unsafe { catch_panic(|| panic!("panicked with {}", number)) }
+        }
+
+        let mut decl = ClassDecl::new("CatchPanicObject", class!(NSObject)).unwrap();
+        unsafe {
+            decl.add_class_method(
+                sel!(panicWithNumber:),
+                panicking as extern "C" fn(&Class, Sel, u32) -> u32,
+            );
+        }
+        let cls = decl.register();
+
+        let result = unsafe {
+            catch(|| {
+                let _: u32 = msg_send![cls, panicWithNumber: 42u32];
+            })
+        };
+        let exception = result.unwrap_err().unwrap();
+        let name: *mut Object = unsafe { msg_send![exception, name] };
+        assert_eq!(to_str(name), "RustPanic");
+        let reason: *mut Object = unsafe { msg_send![exception, reason] };
+        assert_eq!(to_str(reason), "panicked with 42");
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use std::ffi::CString;
+
+use crate::runtime::Sel;
+use crate::{ffi, Encoding};
+
+/// The memory management semantics of a declared property's setter.
+///
+/// See Apple's [documentation on property attributes][attrs].
+///
+/// [attrs]: https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/ObjCRuntimeGuide/Articles/ocrtPropertyIntrospection.html
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PropertySemantics {
+    /// The value is simply assigned (the `assign` attribute).
+    ///
+    /// This is the default, and the only valid option for non-object types.
+    #[default]
+    Assign,
+    /// The value is retained (the `strong` or `retain` attribute).
+    Strong,
+    /// A copy of the value is made (the `copy` attribute).
+    Copy,
+    /// The value is weakly referenced (the `weak` attribute).
+    Weak,
+}
+
+/// The attributes of a declared property.
+///
+/// The type of the property is not specified here, but is instead given as a
+/// type parameter when declaring the property.
+///
+/// # Example
+///
+/// ```
+/// use objc2::declare::{PropertyAttributes, PropertySemantics};
+///
+/// let attributes = PropertyAttributes {
+///     nonatomic: true,
+///     semantics: PropertySemantics::Copy,
+///     ivar: Some("_name"),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PropertyAttributes<'a> {
+    /// Whether the property is `readonly`.
+    pub readonly: bool,
+    /// Whether the property is `nonatomic`.
+    pub nonatomic: bool,
+    /// The memory management semantics of the property.
+    pub semantics: PropertySemantics,
+    /// A custom getter selector, or [`None`] to use the default (the name of
+    /// the property).
+    pub getter: Option<Sel>,
+    /// A custom setter selector, or [`None`] to use the default (`setName:`).
+    pub setter: Option<Sel>,
+    /// The name of the instance variable backing the property, if any.
+    pub ivar: Option<&'a str>,
+}
+
+/// The attributes, converted to the format that the runtime expects.
+pub(crate) struct RawPropertyAttributes {
+    attributes: Vec<ffi::objc_property_attribute_t>,
+    // Owns the strings that `attributes` point into.
+    _strings: Vec<CString>,
+}
+
+impl RawPropertyAttributes {
+    pub(crate) fn as_ptr(&self) -> *const ffi::objc_property_attribute_t {
+        self.attributes.as_ptr()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.attributes.len()
+    }
+}
+
+impl PropertyAttributes<'_> {
+    /// The default getter selector for a property with the given name.
+    pub(crate) fn default_getter(name: &str) -> Sel {
+        Sel::register(name)
+    }
+
+    /// The default setter selector for a property with the given name.
+    pub(crate) fn default_setter(name: &str) -> Sel {
+        let mut chars = name.chars();
+        let setter = match chars.next() {
+            Some(first) => {
+                let mut setter = "set".to_string();
+                setter.extend(first.to_uppercase());
+                setter.push_str(chars.as_str());
+                setter.push(':');
+                setter
+            }
+            None => "set:".to_string(),
+        };
+        Sel::register(&setter)
+    }
+
+    /// Convert the attributes to the list of attributes that e.g.
+    /// `class_addProperty` takes, in the same order as the compiler emits
+    /// them.
+    pub(crate) fn to_raw(self, encoding: &Encoding<'_>) -> RawPropertyAttributes {
+        let mut entries: Vec<(&str, CString)> = Vec::new();
+        let flag = || CString::new("").unwrap();
+
+        entries.push(("T", CString::new(encoding.to_string()).unwrap()));
+        if self.readonly {
+            entries.push(("R", flag()));
+        }
+        match self.semantics {
+            PropertySemantics::Assign => {}
+            PropertySemantics::Strong => entries.push(("&", flag())),
+            PropertySemantics::Copy => entries.push(("C", flag())),
+            PropertySemantics::Weak => entries.push(("W", flag())),
+        }
+        if self.nonatomic {
+            entries.push(("N", flag()));
+        }
+        if let Some(getter) = self.getter {
+            entries.push(("G", CString::new(getter.name()).unwrap()));
+        }
+        if let Some(setter) = self.setter {
+            entries.push(("S", CString::new(setter.name()).unwrap()));
+        }
+        if let Some(ivar) = self.ivar {
+            entries.push(("V", CString::new(ivar).unwrap()));
+        }
+
+        let mut strings = Vec::with_capacity(entries.len() * 2);
+        let mut attributes = Vec::with_capacity(entries.len());
+        for (name, value) in entries {
+            let name = CString::new(name).unwrap();
+            // Moving the `CString` into the vector doesn't move the heap
+            // allocation, so the pointers stay valid.
+            attributes.push(ffi::objc_property_attribute_t {
+                name: name.as_ptr(),
+                value: value.as_ptr(),
+            });
+            strings.push(name);
+            strings.push(value);
+        }
+
+        RawPropertyAttributes {
+            attributes,
+            _strings: strings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+    use crate::Encode;
+
+    fn raw_to_vec(raw: &RawPropertyAttributes) -> Vec<(&str, &str)> {
+        raw.attributes
+            .iter()
+            .map(|attr| unsafe {
+                (
+                    CStr::from_ptr(attr.name).to_str().unwrap(),
+                    CStr::from_ptr(attr.value).to_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_accessors() {
+        assert_eq!(PropertyAttributes::default_getter("foo").name(), "foo");
+        assert_eq!(PropertyAttributes::default_setter("foo").name(), "setFoo:");
+        assert_eq!(
+            PropertyAttributes::default_setter("isEnabled").name(),
+            "setIsEnabled:"
+        );
+    }
+
+    #[test]
+    fn test_to_raw() {
+        let attributes = PropertyAttributes::default();
+        let raw = attributes.to_raw(&u32::ENCODING);
+        assert_eq!(raw_to_vec(&raw), [("T", "I")]);
+
+        let attributes = PropertyAttributes {
+            readonly: true,
+            nonatomic: true,
+            semantics: PropertySemantics::Copy,
+            getter: Some(Sel::register("isFoo")),
+            setter: None,
+            ivar: Some("_foo"),
+        };
+        let raw = attributes.to_raw(&u8::ENCODING);
+        assert_eq!(
+            raw_to_vec(&raw),
+            [
+                ("T", "C"),
+                ("R", ""),
+                ("C", ""),
+                ("N", ""),
+                ("G", "isFoo"),
+                ("V", "_foo")
+            ]
+        );
+        assert_eq!(raw.len(), 6);
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::sync::Mutex;
+
+use super::{catch_panic, ClassExtension};
+use crate::runtime::{Bool, Class, Object, Sel};
+
+pub(super) type MethodResolver = Arc<dyn Fn(&mut ClassExtension, Sel) -> bool + Send + Sync>;
+pub(super) type ForwardingTarget = Arc<dyn Fn(&Object, Sel) -> *mut Object + Send + Sync>;
+
+#[derive(Default, Clone)]
+pub(super) struct Resolvers {
+    pub(super) instance_method: Option<MethodResolver>,
+    pub(super) class_method: Option<MethodResolver>,
+    pub(super) forwarding_target: Option<ForwardingTarget>,
+}
+
+impl Resolvers {
+    pub(super) fn is_empty(&self) -> bool {
+        self.instance_method.is_none()
+            && self.class_method.is_none()
+            && self.forwarding_target.is_none()
+    }
+}
+
+// The closures are stored in a global registry keyed by the class they were
+// declared on, since the method implementations that the runtime calls can't
+// carry any state of their own.
+//
+// A `Vec` is fine here, since only a few classes will use this.
+static REGISTRY: Mutex<Vec<(usize, Resolvers)>> = Mutex::new(Vec::new());
+
+/// Make the resolvers available to the registered class.
+pub(super) fn register(cls: &'static Class, resolvers: Resolvers) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    registry.push((cls as *const Class as usize, resolvers));
+}
+
+/// Remove the resolvers of a class that is being disposed.
+pub(super) fn unregister(cls: &Class) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let ptr = cls as *const Class as usize;
+    registry.retain(|(key, _)| *key != ptr);
+}
+
+/// Make the resolvers of the original class available to its duplicate.
+#[cfg(apple)]
+pub(super) fn duplicate(original: &Class, duplicate: &'static Class) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let ptr = original as *const Class as usize;
+    let resolvers = registry
+        .iter()
+        .find(|(key, _)| *key == ptr)
+        .map(|(_, resolvers)| resolvers.clone());
+    if let Some(resolvers) = resolvers {
+        registry.push((duplicate as *const Class as usize, resolvers));
+    }
+}
+
+/// Find the resolver on the class or the nearest superclass that has one.
+///
+/// The lock is not held while the resolver is called, since it may itself
+/// trigger method resolution.
+fn find<T>(cls: &Class, get: impl Fn(&Resolvers) -> Option<T>) -> Option<T> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cls = Some(cls);
+    while let Some(current) = cls {
+        let ptr = current as *const Class as usize;
+        let found = registry
+            .iter()
+            .find(|(key, _)| *key == ptr)
+            .and_then(|(_, resolvers)| get(resolvers));
+        if found.is_some() {
+            return found;
+        }
+        cls = current.superclass();
+    }
+    None
+}
+
+fn resolve(cls: &Class, sel: Sel, get: impl Fn(&Resolvers) -> Option<MethodResolver>) -> Bool {
+    // SAFETY: Classes are never deallocated once registered.
+    let cls: &'static Class = unsafe { &*(cls as *const Class) };
+    let resolved = match find(cls, get) {
+        Some(resolver) => resolver(&mut ClassExtension::new(cls), sel),
+        None => false,
+    };
+    Bool::new(resolved)
+}
+
+// The closures are user code, so we make sure that panics in them don't
+// unwind into the runtime.
+
+/// Implementation of `+resolveInstanceMethod:`.
+pub(super) extern "C" fn resolve_instance_method(cls: &Class, _cmd: Sel, sel: Sel) -> Bool {
+    let body = || resolve(cls, sel, |r| r.instance_method.clone());
+    unsafe { catch_panic(body) }
+}
+
+/// Implementation of `+resolveClassMethod:`.
+pub(super) extern "C" fn resolve_class_method(cls: &Class, _cmd: Sel, sel: Sel) -> Bool {
+    let body = || resolve(cls, sel, |r| r.class_method.clone());
+    unsafe { catch_panic(body) }
+}
+
+/// Implementation of `-forwardingTargetForSelector:`.
+pub(super) extern "C" fn forwarding_target(this: &Object, _cmd: Sel, sel: Sel) -> *mut Object {
+    let body = || match find(this.class(), |r| r.forwarding_target.clone()) {
+        Some(target) => target(this, sel),
+        None => core::ptr::null_mut(),
+    };
+    unsafe { catch_panic(body) }
+}
@@ -0,0 +1,365 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::fmt;
+use core::iter;
+use core::slice;
+use std::error::Error;
+use std::os::raw::c_uint;
+
+use super::method_type_encoding;
+use crate::ffi;
+use crate::runtime::{Bool, Class, MethodDescription, Object, Protocol, Sel};
+use crate::{Encode, Encoding};
+
+extern "C" {
+    // Declared here instead of going through `malloc_buf`, so that
+    // conformance is checked without the `malloc` feature.
+    fn free(ptr: *mut c_void);
+}
+
+/// Copies a list returned by one of the runtime's `copy` functions into a
+/// `Vec`, and frees the list.
+///
+/// # Safety
+///
+/// `list` must be NULL, or a list of `count` elements allocated with
+/// `malloc`.
+unsafe fn copy_list<T: Copy>(list: *mut T, count: c_uint) -> Vec<T> {
+    if list.is_null() {
+        return Vec::new();
+    }
+    let result = unsafe { slice::from_raw_parts(list, count as usize) }.to_vec();
+    unsafe { free(list.cast()) };
+    result
+}
+
+/// Like [`Protocol::method_descriptions`], but without requiring the
+/// `malloc` feature.
+fn method_descriptions(
+    protocol: &Protocol,
+    is_required: bool,
+    is_instance_method: bool,
+) -> Vec<MethodDescription> {
+    let descriptions = unsafe {
+        let mut count: c_uint = 0;
+        let descriptions = ffi::protocol_copyMethodDescriptionList(
+            protocol.as_ptr(),
+            Bool::new(is_required).as_raw(),
+            Bool::new(is_instance_method).as_raw(),
+            &mut count,
+        );
+        copy_list(descriptions, count)
+    };
+    descriptions
+        .into_iter()
+        .filter_map(|desc| unsafe { MethodDescription::from_raw(desc) })
+        .collect()
+}
+
+/// Like [`Protocol::adopted_protocols`], but without requiring the `malloc`
+/// feature.
+fn adopted_protocols(protocol: &Protocol) -> Vec<&'static Protocol> {
+    let protocols = unsafe {
+        let mut count: c_uint = 0;
+        let protocols = ffi::protocol_copyProtocolList(protocol.as_ptr(), &mut count);
+        copy_list(protocols, count)
+    };
+    // SAFETY: Protocols are never deallocated.
+    protocols
+        .into_iter()
+        .map(|proto| unsafe { &*(proto as *const Protocol) })
+        .collect()
+}
+
+/// A method that was added to a [`ClassDecl`][super::ClassDecl], recorded so
+/// that it can be verified when the class is registered.
+#[derive(Clone, Copy)]
+pub(super) struct AddedMethod {
+    pub(super) sel: Sel,
+    pub(super) is_instance_method: bool,
+    pub(super) ret: Encoding<'static>,
+    pub(super) args: &'static [Encoding<'static>],
+}
+
+impl AddedMethod {
+    fn type_encoding(&self) -> String {
+        let types = method_type_encoding(&self.ret, self.args);
+        types.into_string().unwrap()
+    }
+}
+
+/// A single problem found while verifying a declared class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegisterErrorKind {
+    /// A method overrides a method on the superclass, but the type encodings
+    /// of the two methods differ.
+    MismatchedOverride {
+        /// The selector of the method.
+        sel: Sel,
+        /// Whether the method is an instance or a class method.
+        is_instance_method: bool,
+        /// The type encoding of the superclass' method.
+        expected: String,
+        /// The type encoding of the added method.
+        actual: String,
+    },
+    /// A method implements a method described by an adopted protocol, but
+    /// the type encodings of the two differ.
+    MismatchedProtocolMethod {
+        /// The protocol that describes the method.
+        protocol: &'static Protocol,
+        /// The selector of the method.
+        sel: Sel,
+        /// Whether the method is an instance or a class method.
+        is_instance_method: bool,
+        /// The type encoding in the protocol's method description.
+        expected: String,
+        /// The type encoding of the added method.
+        actual: String,
+    },
+    /// A method required by an adopted protocol (or a protocol that it
+    /// inherits) is not implemented by the class or its superclasses.
+    MissingProtocolMethod {
+        /// The protocol that requires the method.
+        protocol: &'static Protocol,
+        /// The selector of the method.
+        sel: Sel,
+        /// Whether the method is an instance or a class method.
+        is_instance_method: bool,
+    },
+}
+
+impl fmt::Display for RegisterErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn prefix(is_instance_method: bool) -> char {
+            if is_instance_method {
+                '-'
+            } else {
+                '+'
+            }
+        }
+
+        match self {
+            Self::MismatchedOverride {
+                sel,
+                is_instance_method,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "method {}[{:?}] has type encoding {}, but the overridden method on the superclass has {}",
+                prefix(*is_instance_method),
+                sel,
+                actual,
+                expected,
+            ),
+            Self::MismatchedProtocolMethod {
+                protocol,
+                sel,
+                is_instance_method,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "method {}[{:?}] has type encoding {}, but protocol {:?} declares it with {}",
+                prefix(*is_instance_method),
+                sel,
+                actual,
+                protocol,
+                expected,
+            ),
+            Self::MissingProtocolMethod {
+                protocol,
+                sel,
+                is_instance_method,
+            } => write!(
+                f,
+                "method {}[{:?}] required by protocol {:?} is not implemented",
+                prefix(*is_instance_method),
+                sel,
+                protocol,
+            ),
+        }
+    }
+}
+
+/// An error returned from [`ClassDecl::try_register`][super::ClassDecl::try_register]
+/// when the declared class failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterError {
+    class_name: String,
+    errors: Vec<RegisterErrorKind>,
+}
+
+impl RegisterError {
+    /// The name of the class that failed verification.
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The problems that were found (there is always at least one).
+    pub fn errors(&self) -> &[RegisterErrorKind] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed verifying class {}:", self.class_name)?;
+        for error in &self.errors {
+            write!(f, "\n- {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RegisterError {}
+
+/// Check whether the given method type encoding (which may contain stack
+/// offsets, like the ones emitted by the compiler) matches the given return
+/// and argument encodings.
+pub(crate) fn type_encoding_matches(
+    types: &str,
+    ret: &Encoding<'_>,
+    args: &[Encoding<'_>],
+) -> bool {
+    let self_and_cmd = [<*mut Object>::ENCODING, Sel::ENCODING];
+    let mut s = types;
+    for enc in iter::once(ret).chain(&self_and_cmd).chain(args) {
+        s = match enc.equivalent_to_start_of_str(s) {
+            Some(s) => s,
+            None => return false,
+        };
+        s = s.trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    s.is_empty()
+}
+
+fn check_protocol_method(
+    errors: &mut Vec<RegisterErrorKind>,
+    protocol: &'static Protocol,
+    method: &AddedMethod,
+    expected: &str,
+) {
+    if !type_encoding_matches(expected, &method.ret, method.args) {
+        errors.push(RegisterErrorKind::MismatchedProtocolMethod {
+            protocol,
+            sel: method.sel,
+            is_instance_method: method.is_instance_method,
+            expected: expected.into(),
+            actual: method.type_encoding(),
+        });
+    }
+}
+
+/// Verify the methods described by the protocol and all the protocols it
+/// inherits from.
+fn verify_protocol(
+    cls: &Class,
+    methods: &[AddedMethod],
+    protocol: &'static Protocol,
+    visited: &mut Vec<&'static Protocol>,
+    errors: &mut Vec<RegisterErrorKind>,
+) {
+    if visited.contains(&protocol) {
+        return;
+    }
+    visited.push(protocol);
+
+    for (is_required, is_instance_method) in
+        [(true, true), (true, false), (false, true), (false, false)]
+    {
+        for desc in method_descriptions(protocol, is_required, is_instance_method) {
+            let sel = desc.name();
+            let added = methods
+                .iter()
+                .find(|m| m.sel == sel && m.is_instance_method == is_instance_method);
+            if let Some(method) = added {
+                check_protocol_method(errors, protocol, method, desc.type_encoding());
+            } else if is_required {
+                let implemented = if is_instance_method {
+                    cls.instance_method(sel)
+                } else {
+                    cls.metaclass().instance_method(sel)
+                };
+                if implemented.is_none() {
+                    errors.push(RegisterErrorKind::MissingProtocolMethod {
+                        protocol,
+                        sel,
+                        is_instance_method,
+                    });
+                }
+            }
+        }
+    }
+
+    for inherited in adopted_protocols(protocol) {
+        verify_protocol(cls, methods, inherited, visited, errors);
+    }
+}
+
+/// Verify the methods added to `cls` against the methods of its superclass,
+/// and verify that `cls` conforms to its adopted protocols.
+pub(super) fn verify_class(
+    cls: &Class,
+    methods: &[AddedMethod],
+    protocols: &[&'static Protocol],
+) -> Result<(), RegisterError> {
+    let mut errors = Vec::new();
+
+    for method in methods {
+        let superclass = cls.superclass().and_then(|superclass| {
+            if method.is_instance_method {
+                superclass.instance_method(method.sel)
+            } else {
+                superclass.metaclass().instance_method(method.sel)
+            }
+        });
+        if let Some(overridden) = superclass {
+            let expected = overridden.type_encoding();
+            if !type_encoding_matches(expected, &method.ret, method.args) {
+                errors.push(RegisterErrorKind::MismatchedOverride {
+                    sel: method.sel,
+                    is_instance_method: method.is_instance_method,
+                    expected: expected.into(),
+                    actual: method.type_encoding(),
+                });
+            }
+        }
+    }
+
+    let mut visited = Vec::new();
+    for &protocol in protocols {
+        verify_protocol(cls, methods, protocol, &mut visited, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RegisterError {
+            class_name: cls.name().into(),
+            errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_encoding_matches() {
+        let ret = u32::ENCODING;
+        let args = [i8::ENCODING];
+        assert!(type_encoding_matches("I@:c", &ret, &args));
+        assert!(type_encoding_matches("I20@0:8c16", &ret, &args));
+        assert!(type_encoding_matches("rI@:c", &ret, &args));
+
+        assert!(!type_encoding_matches("i@:c", &ret, &args));
+        assert!(!type_encoding_matches("I@:", &ret, &args));
+        assert!(!type_encoding_matches("I@:cc", &ret, &args));
+        assert!(!type_encoding_matches("I:@c", &ret, &args));
+    }
+}
@@ -0,0 +1,613 @@
+//! Reconstructing Objective-C declarations from runtime metadata.
+//!
+//! This is similar to the `class-dump` tool; given a [`Class`] or a
+//! [`Protocol`], it renders an `@interface` or `@protocol` declaration from
+//! the instance variables, properties, methods and adopted protocols that the
+//! runtime knows about, with the type encodings decoded into C types.
+//!
+//! Note that the result is only an approximation of the original
+//! declaration; the type encodings don't include everything (e.g. the names
+//! of method arguments, or the difference between `BOOL` and `signed char`).
+//!
+//! This is only available when the `malloc` feature is enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use objc2::class;
+//! use objc2::dump::ClassInterface;
+//!
+//! println!("{}", ClassInterface::new(class!(NSObject)));
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::runtime::{Class, Property, Protocol, Sel};
+
+/// A C type, split around the place where the name goes in a declaration
+/// (e.g. `int` and `[4]` for `int name[4]`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CType {
+    prefix: String,
+    suffix: String,
+}
+
+impl CType {
+    fn simple(name: &str) -> Self {
+        Self {
+            prefix: name.into(),
+            suffix: String::new(),
+        }
+    }
+
+    fn pointer(self) -> Self {
+        let prefix = if self.prefix.ends_with('*') {
+            format!("{}*", self.prefix)
+        } else {
+            format!("{} *", self.prefix)
+        };
+        Self {
+            prefix,
+            suffix: self.suffix,
+        }
+    }
+
+    /// Declare a variable (or field) of this type with the given name.
+    fn declare(&self, name: &str) -> String {
+        if self.prefix.ends_with('*') || self.prefix.ends_with('(') {
+            format!("{}{}{}", self.prefix, name, self.suffix)
+        } else {
+            format!("{} {}{}", self.prefix, name, self.suffix)
+        }
+    }
+}
+
+impl fmt::Display for CType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix, self.suffix)
+    }
+}
+
+fn split_number(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Skip the stack offsets that method type encodings may contain.
+fn skip_offset(s: &str) -> &str {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    split_number(s).1
+}
+
+/// Parse the fields of a struct or union, up to and including `end`.
+fn parse_fields(mut s: &str, end: char) -> Option<(Vec<CType>, &str)> {
+    let mut fields = Vec::new();
+    loop {
+        if let Some(rest) = s.strip_prefix(end) {
+            return Some((fields, rest));
+        }
+        // Field names may be given in quotes before the type
+        if let Some(rest) = s.strip_prefix('"') {
+            let end = rest.find('"')?;
+            s = &rest[end + 1..];
+        }
+        let (field, rest) = parse_type(s)?;
+        fields.push(field);
+        s = rest;
+    }
+}
+
+/// Parse a struct or union, after the opening brace.
+fn parse_aggregate<'a>(s: &'a str, keyword: &str, end: char) -> Option<(CType, &'a str)> {
+    let name_end = s.find(['=', end])?;
+    let name = &s[..name_end];
+    let s = &s[name_end..];
+    let (fields, rest) = match s.strip_prefix('=') {
+        Some(s) => parse_fields(s, end)?,
+        None => (Vec::new(), &s[1..]),
+    };
+
+    let ty = if name.is_empty() || name == "?" {
+        let fields: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| format!("{};", field.declare(&format!("field{}", i))))
+            .collect();
+        CType::simple(&format!("{} {{ {} }}", keyword, fields.join(" ")))
+    } else {
+        CType::simple(&format!("{} {}", keyword, name))
+    };
+    Some((ty, rest))
+}
+
+/// Parse a single type at the start of the given encoding, and return the
+/// rest of the string.
+fn parse_type(s: &str) -> Option<(CType, &str)> {
+    let mut qualifiers = String::new();
+    let mut s = s;
+    loop {
+        let qualifier = match s.chars().next()? {
+            'r' => "const",
+            'n' => "in",
+            'N' => "inout",
+            'o' => "out",
+            'O' => "bycopy",
+            'R' => "byref",
+            'V' => "oneway",
+            'A' => "_Atomic",
+            _ => break,
+        };
+        qualifiers.push_str(qualifier);
+        qualifiers.push(' ');
+        s = &s[1..];
+    }
+
+    let c = s.chars().next()?;
+    let rest = &s[c.len_utf8()..];
+    let (mut ty, rest) = match c {
+        'c' => (CType::simple("char"), rest),
+        's' => (CType::simple("short"), rest),
+        'i' => (CType::simple("int"), rest),
+        'l' => (CType::simple("long"), rest),
+        'q' => (CType::simple("long long"), rest),
+        'C' => (CType::simple("unsigned char"), rest),
+        'S' => (CType::simple("unsigned short"), rest),
+        'I' => (CType::simple("unsigned int"), rest),
+        'L' => (CType::simple("unsigned long"), rest),
+        'Q' => (CType::simple("unsigned long long"), rest),
+        'f' => (CType::simple("float"), rest),
+        'd' => (CType::simple("double"), rest),
+        'D' => (CType::simple("long double"), rest),
+        'B' => (CType::simple("bool"), rest),
+        'v' => (CType::simple("void"), rest),
+        '*' => (CType::simple("char *"), rest),
+        '#' => (CType::simple("Class"), rest),
+        ':' => (CType::simple("SEL"), rest),
+        '?' => (CType::simple("void /* unknown */"), rest),
+        'j' => {
+            let (inner, rest) = parse_type(rest)?;
+            (CType::simple(&format!("_Complex {}", inner)), rest)
+        }
+        '@' => {
+            if let Some(rest) = rest.strip_prefix('?') {
+                (CType::simple("id /* block */"), rest)
+            } else if let Some(rest) = rest.strip_prefix('"') {
+                let end = rest.find('"')?;
+                let name = &rest[..end];
+                let ty = if name.is_empty() {
+                    CType::simple("id")
+                } else if name.starts_with('<') {
+                    CType::simple(&format!("id{}", name))
+                } else {
+                    CType::simple(name).pointer()
+                };
+                (ty, &rest[end + 1..])
+            } else {
+                (CType::simple("id"), rest)
+            }
+        }
+        '^' => {
+            if let Some(rest) = rest.strip_prefix('?') {
+                // Function pointer
+                let ty = CType {
+                    prefix: "void (*".into(),
+                    suffix: ")(void)".into(),
+                };
+                (ty, rest)
+            } else {
+                let (inner, rest) = parse_type(rest)?;
+                (inner.pointer(), rest)
+            }
+        }
+        // Apple encodes bitfields as `b<bits>`.
+        #[cfg(not(gnustep))]
+        'b' => {
+            let (bits, rest) = split_number(rest);
+            let ty = CType {
+                prefix: "unsigned int".into(),
+                suffix: format!(" : {}", bits),
+            };
+            (ty, rest)
+        }
+        // GNUStep encodes bitfields as `b<offset><type><bits>`.
+        #[cfg(gnustep)]
+        'b' => {
+            let (_offset, rest) = split_number(rest);
+            let (inner, rest) = parse_type(rest)?;
+            let (bits, rest) = split_number(rest);
+            if bits.is_empty() {
+                return None;
+            }
+            let ty = CType {
+                prefix: inner.to_string(),
+                suffix: format!(" : {}", bits),
+            };
+            (ty, rest)
+        }
+        '[' => {
+            let (len, rest) = split_number(rest);
+            let (inner, rest) = parse_type(rest)?;
+            let rest = rest.strip_prefix(']')?;
+            let ty = CType {
+                prefix: inner.prefix,
+                suffix: format!("[{}]{}", len, inner.suffix),
+            };
+            (ty, rest)
+        }
+        '{' => parse_aggregate(rest, "struct", '}')?,
+        '(' => parse_aggregate(rest, "union", ')')?,
+        _ => return None,
+    };
+
+    if !qualifiers.is_empty() {
+        ty.prefix.insert_str(0, &qualifiers);
+    }
+    Some((ty, rest))
+}
+
+/// Decodes a type encoding into the corresponding C type.
+///
+/// Returns [`None`] if the encoding is invalid, or contains more than one
+/// type.
+///
+/// # Example
+///
+/// ```
+/// use objc2::dump::decode_type;
+///
+/// assert_eq!(decode_type("^i").as_deref(), Some("int *"));
+/// assert_eq!(decode_type("@\"NSString\"").as_deref(), Some("NSString *"));
+/// assert_eq!(decode_type("{CGPoint=dd}").as_deref(), Some("struct CGPoint"));
+/// ```
+pub fn decode_type(encoding: &str) -> Option<String> {
+    match parse_type(encoding)? {
+        (ty, "") => Some(ty.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse a method type encoding, returning the return type and the types
+/// of the arguments (excluding `self` and `_cmd`).
+fn parse_method_types(types: &str) -> Option<(CType, Vec<CType>)> {
+    let (ret, rest) = parse_type(types)?;
+    let mut rest = skip_offset(rest);
+    let mut args = Vec::new();
+    while !rest.is_empty() {
+        let (arg, r) = parse_type(rest)?;
+        args.push(arg);
+        rest = skip_offset(r);
+    }
+    if args.len() < 2 {
+        return None;
+    }
+    args.drain(..2);
+    Some((ret, args))
+}
+
+fn write_method(
+    f: &mut fmt::Formatter<'_>,
+    is_instance_method: bool,
+    sel: Sel,
+    types: &str,
+) -> fmt::Result {
+    let prefix = if is_instance_method { '-' } else { '+' };
+    let name = sel.name();
+    let parsed = parse_method_types(types).filter(|(_, args)| {
+        // The number of arguments must match the selector
+        args.len() == name.matches(':').count()
+    });
+    let (ret, args) = match parsed {
+        Some(parsed) => parsed,
+        None => return writeln!(f, "{} {}; // Unknown types: {}", prefix, name, types),
+    };
+
+    write!(f, "{} ({})", prefix, ret)?;
+    if args.is_empty() {
+        write!(f, "{}", name)?;
+    } else {
+        for (i, (part, arg)) in name.split_terminator(':').zip(&args).enumerate() {
+            if i != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}:({})arg{}", part, arg, i)?;
+        }
+    }
+    writeln!(f, ";")
+}
+
+fn write_property(f: &mut fmt::Formatter<'_>, property: &Property) -> fmt::Result {
+    let mut ty = None;
+    let mut attributes = Vec::new();
+    for attribute in property.attributes().split(',') {
+        let (code, value) = attribute.split_at(attribute.len().min(1));
+        match code {
+            "T" => ty = parse_type(value).map(|(ty, _)| ty),
+            "R" => attributes.push("readonly".into()),
+            "C" => attributes.push("copy".into()),
+            "&" => attributes.push("strong".into()),
+            "W" => attributes.push("weak".into()),
+            "N" => attributes.push("nonatomic".into()),
+            "G" => attributes.push(format!("getter={}", value)),
+            "S" => attributes.push(format!("setter={}", value)),
+            _ => {}
+        }
+    }
+
+    write!(f, "@property ")?;
+    if !attributes.is_empty() {
+        write!(f, "({}) ", attributes.join(", "))?;
+    }
+    match ty {
+        Some(ty) => writeln!(f, "{};", ty.declare(property.name())),
+        None => writeln!(
+            f,
+            "id {}; // Unknown attributes: {}",
+            property.name(),
+            property.attributes()
+        ),
+    }
+}
+
+fn write_protocol_list(f: &mut fmt::Formatter<'_>, protocols: &[&Protocol]) -> fmt::Result {
+    if !protocols.is_empty() {
+        let names: Vec<&str> = protocols.iter().map(|proto| proto.name()).collect();
+        write!(f, " <{}>", names.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Renders an `@interface` declaration for a class.
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug, Clone, Copy)]
+pub struct ClassInterface<'a> {
+    cls: &'a Class,
+}
+
+impl<'a> ClassInterface<'a> {
+    /// Creates a new [`ClassInterface`] for the given class.
+    pub fn new(cls: &'a Class) -> Self {
+        Self { cls }
+    }
+}
+
+impl fmt::Display for ClassInterface<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cls = self.cls;
+        write!(f, "@interface {}", cls.name())?;
+        if let Some(superclass) = cls.superclass() {
+            write!(f, " : {}", superclass.name())?;
+        }
+        write_protocol_list(f, &cls.adopted_protocols())?;
+        writeln!(f)?;
+
+        let ivars = cls.instance_variables();
+        if !ivars.is_empty() {
+            writeln!(f, "{{")?;
+            for ivar in ivars.iter() {
+                match parse_type(ivar.type_encoding()) {
+                    Some((ty, _)) => writeln!(f, "    {};", ty.declare(ivar.name()))?,
+                    None => writeln!(
+                        f,
+                        "    // {} has unknown type {}",
+                        ivar.name(),
+                        ivar.type_encoding()
+                    )?,
+                }
+            }
+            writeln!(f, "}}")?;
+        }
+
+        let mut properties: Vec<&Property> = cls.properties().iter().copied().collect();
+        if !properties.is_empty() {
+            properties.sort_by(|a, b| a.name().cmp(b.name()));
+            writeln!(f)?;
+            for property in properties {
+                write_property(f, property)?;
+            }
+        }
+
+        for (is_instance_method, cls) in [(false, cls.metaclass()), (true, cls)] {
+            let mut methods: Vec<_> = cls.instance_methods().iter().copied().collect();
+            if !methods.is_empty() {
+                methods.sort_by(|a, b| a.name().name().cmp(b.name().name()));
+                writeln!(f)?;
+                for method in methods {
+                    write_method(f, is_instance_method, method.name(), method.type_encoding())?;
+                }
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "@end")
+    }
+}
+
+/// Renders a `@protocol` declaration for a protocol.
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug, Clone, Copy)]
+pub struct ProtocolInterface<'a> {
+    proto: &'a Protocol,
+}
+
+impl<'a> ProtocolInterface<'a> {
+    /// Creates a new [`ProtocolInterface`] for the given protocol.
+    pub fn new(proto: &'a Protocol) -> Self {
+        Self { proto }
+    }
+
+    fn write_methods(&self, f: &mut fmt::Formatter<'_>, is_required: bool) -> fmt::Result {
+        for is_instance_method in [false, true] {
+            let mut methods = self
+                .proto
+                .method_descriptions(is_required, is_instance_method);
+            if !methods.is_empty() {
+                methods.sort_by(|a, b| a.name().name().cmp(b.name().name()));
+                writeln!(f)?;
+                for method in methods {
+                    write_method(f, is_instance_method, method.name(), method.type_encoding())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProtocolInterface<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "@protocol {}", self.proto.name())?;
+        write_protocol_list(f, &self.proto.adopted_protocols())?;
+        writeln!(f)?;
+
+        let mut properties: Vec<&Property> = self.proto.properties().iter().copied().collect();
+        if !properties.is_empty() {
+            properties.sort_by(|a, b| a.name().cmp(b.name()));
+            writeln!(f)?;
+            for property in properties {
+                write_property(f, property)?;
+            }
+        }
+
+        self.write_methods(f, true)?;
+        let has_optional = [false, true].iter().any(|&is_instance_method| {
+            !self
+                .proto
+                .method_descriptions(false, is_instance_method)
+                .is_empty()
+        });
+        if has_optional {
+            writeln!(f)?;
+            write!(f, "@optional")?;
+            self.write_methods(f, false)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "@end")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_decode_type() {
+        let cases = [
+            ("c", "char"),
+            ("Q", "unsigned long long"),
+            ("v", "void"),
+            ("*", "char *"),
+            ("r*", "const char *"),
+            ("@", "id"),
+            ("@?", "id /* block */"),
+            ("@\"NSString\"", "NSString *"),
+            ("@\"<NSCopying>\"", "id<NSCopying>"),
+            ("#", "Class"),
+            (":", "SEL"),
+            ("^^i", "int **"),
+            ("^?", "void (*)(void)"),
+            ("[4f]", "float[4]"),
+            ("{CGPoint=dd}", "struct CGPoint"),
+            ("^{CGPoint}", "struct CGPoint *"),
+            ("{?=id}", "struct { int field0; double field1; }"),
+            (
+                "{?=\"x\"i\"y\"[2c]}",
+                "struct { int field0; char field1[2]; }",
+            ),
+            ("(?=iB)", "union { int field0; bool field1; }"),
+            #[cfg(not(gnustep))]
+            ("b3", "unsigned int : 3"),
+            #[cfg(not(gnustep))]
+            (
+                "{?=b1b3}",
+                "struct { unsigned int field0 : 1; unsigned int field1 : 3; }",
+            ),
+            #[cfg(gnustep)]
+            ("b0I3", "unsigned int : 3"),
+            #[cfg(gnustep)]
+            (
+                "{?=b0I1b1c3i}",
+                "struct { unsigned int field0 : 1; char field1 : 3; int field2; }",
+            ),
+        ];
+        for (encoding, expected) in cases {
+            assert_eq!(
+                decode_type(encoding).as_deref(),
+                Some(expected),
+                "{}",
+                encoding
+            );
+        }
+
+        assert_eq!(decode_type(""), None);
+        assert_eq!(decode_type("ii"), None);
+        assert_eq!(decode_type("{CGPoint=dd"), None);
+        assert_eq!(decode_type("^"), None);
+        #[cfg(gnustep)]
+        assert_eq!(decode_type("b0I"), None);
+    }
+
+    #[test]
+    fn test_parse_method_types() {
+        let (ret, args) = parse_method_types("i24@0:8i16i20").unwrap();
+        assert_eq!(ret.to_string(), "int");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].to_string(), "int");
+        assert!(parse_method_types("v@").is_none());
+    }
+
+    #[test]
+    fn test_protocol_interface() {
+        let expected = "\
+@protocol CustomSubProtocol <CustomProtocol>
+
+- (unsigned int)calculateFoo:(unsigned int)arg0;
+
+@end
+";
+        let proto = test_utils::custom_subprotocol();
+        assert_eq!(ProtocolInterface::new(proto).to_string(), expected);
+
+        let expected = "\
+@protocol CustomProtocol
+
+@property (readonly) unsigned int bar;
+
++ (int)addNumber:(int)arg0 toNumber:(int)arg1;
+
+- (void)setBar:(int)arg0;
+
+@optional
+- (char *)getName;
+
+@end
+";
+        let proto = test_utils::custom_protocol();
+        assert_eq!(ProtocolInterface::new(proto).to_string(), expected);
+    }
+
+    #[test]
+    fn test_class_interface() {
+        let cls = test_utils::custom_class();
+        let interface = ClassInterface::new(cls).to_string();
+        assert!(interface.starts_with("@interface CustomObject <CustomProtocol>\n{\n"));
+        assert!(interface.contains("    unsigned int _foo;\n"));
+        assert!(interface.contains("@property (nonatomic) unsigned int foo;\n"));
+        assert!(interface.contains("+ (unsigned int)classFoo;\n"));
+        assert!(interface.contains("+ (int)addNumber:(int)arg0 toNumber:(int)arg1;\n"));
+        assert!(interface.contains("- (void)setFoo:(unsigned int)arg0;\n"));
+        assert!(interface.contains("- (struct CustomStruct)customStruct;\n"));
+        assert!(interface.ends_with("\n@end\n"));
+
+        let subclass = test_utils::custom_subclass();
+        let interface = ClassInterface::new(subclass).to_string();
+        assert!(interface.starts_with("@interface CustomSubclassObject : CustomObject\n"));
+    }
+}
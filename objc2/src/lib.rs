@@ -82,22 +82,35 @@ pub use objc_sys as ffi;
 
 pub use objc2_encode::{Encode, EncodeArguments, Encoding, RefEncode};
 
-pub use crate::message::{Message, MessageArguments, MessageError, MessageReceiver};
+#[cfg(feature = "malloc")]
+pub use crate::message::VerificationError;
+pub use crate::message::{
+    CachedImp, Message, MessageArguments, MessageError, MessageReceiver, SendError, TypedSel,
+    VariadicArgument, VariadicArguments, VariadicMessageArguments,
+};
 
 pub use crate::cache::CachedClass as __CachedClass;
+pub use crate::cache::CachedProtocol as __CachedProtocol;
 pub use crate::cache::CachedSel as __CachedSel;
 
 #[macro_use]
 mod macros;
 
+#[doc(hidden)]
+pub mod __macro_helpers;
+
 mod bool;
 mod cache;
 pub mod declare;
+#[cfg(feature = "malloc")]
+pub mod dump;
 #[cfg(feature = "exception")]
 pub mod exception;
 mod message;
 pub mod rc;
 pub mod runtime;
+#[cfg(feature = "trace_message")]
+pub mod trace;
 
 #[cfg(test)]
 mod test_utils;
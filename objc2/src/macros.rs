@@ -4,10 +4,9 @@
 ///
 /// Panics if no class with the given name can be found.
 ///
-/// To check for a class that may not exist, use [`Class::get`].
+/// To check for a class that may not exist, use [`get_class!`].
 ///
 /// [`Class`]: crate::runtime::Class
-/// [`Class::get`]: crate::runtime::Class::get
 ///
 /// # Examples
 ///
@@ -17,14 +16,100 @@
 /// ```
 #[macro_export]
 macro_rules! class {
+    ($name:ident) => {{
+        match $crate::get_class!($name) {
+            Some(cls) => cls,
+            None => panic!("Class with name {} could not be found", stringify!($name)),
+        }
+    }};
+}
+
+/// Gets a reference to a [`Class`] from the given name, or [`None`] if no
+/// class with the given name can be found.
+///
+/// Like [`class!`], the class is cached after it has been found, which makes
+/// this cheaper than [`Class::get`] when called repeatedly.
+///
+/// [`Class`]: crate::runtime::Class
+/// [`Class::get`]: crate::runtime::Class::get
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::get_class;
+/// if let Some(cls) = get_class!(NSUserNotificationCenter) {
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! get_class {
     ($name:ident) => {{
         static CLASS: $crate::__CachedClass = $crate::__CachedClass::new();
         let name = concat!(stringify!($name), '\0');
         #[allow(unused_unsafe)]
-        let cls = unsafe { CLASS.get(name) };
-        match cls {
-            Some(cls) => cls,
-            None => panic!("Class with name {} could not be found", stringify!($name)),
+        unsafe {
+            CLASS.get(name)
+        }
+    }};
+}
+
+/// Gets a reference to a [`Protocol`] from the given name.
+///
+/// The protocol is cached after it has been found, so this is cheap to use
+/// in e.g. conformance checks that are done often.
+///
+/// # Panics
+///
+/// Panics if no protocol with the given name can be found. Note that
+/// protocols are only registered with the runtime if they are used somewhere
+/// in the program (or declared with
+/// [`ProtocolDecl`][crate::declare::ProtocolDecl]).
+///
+/// To check for a protocol that may not exist, use [`get_protocol!`].
+///
+/// [`Protocol`]: crate::runtime::Protocol
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::{class, protocol};
+/// let proto = protocol!(NSObject);
+/// assert!(class!(NSObject).conforms_to(proto));
+/// ```
+#[macro_export]
+macro_rules! protocol {
+    ($name:ident) => {{
+        match $crate::get_protocol!($name) {
+            Some(proto) => proto,
+            None => panic!(
+                "Protocol with name {} could not be found",
+                stringify!($name)
+            ),
+        }
+    }};
+}
+
+/// Gets a reference to a [`Protocol`] from the given name, or [`None`] if no
+/// protocol with the given name can be found.
+///
+/// Like [`protocol!`], the protocol is cached after it has been found.
+///
+/// [`Protocol`]: crate::runtime::Protocol
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::get_protocol;
+/// let proto = get_protocol!(NSCopying);
+/// ```
+#[macro_export]
+macro_rules! get_protocol {
+    ($name:ident) => {{
+        static PROTOCOL: $crate::__CachedProtocol = $crate::__CachedProtocol::new();
+        let name = concat!(stringify!($name), '\0');
+        #[allow(unused_unsafe)]
+        unsafe {
+            PROTOCOL.get(name)
         }
     }};
 }
@@ -58,6 +143,49 @@ macro_rules! sel {
     });
 }
 
+/// Creates a [`TypedSel`] from a selector and the types of its method.
+///
+/// The argument types are given in parentheses after the selector, and the
+/// return type after `->`; it may be left out if the method returns `void`.
+///
+/// [`TypedSel`]: crate::TypedSel
+///
+/// # Panics
+///
+/// If the number of arguments doesn't match the selector.
+///
+/// # Examples
+///
+/// ```
+/// # use objc2::typed_sel;
+/// # use objc2::runtime::Object;
+/// let sel = typed_sel!(description -> *mut Object);
+/// let sel = typed_sel!(setObject:forKey: (*mut Object, *mut Object));
+/// let sel = typed_sel!(isEqual: (*mut Object) -> bool);
+/// ```
+#[macro_export]
+macro_rules! typed_sel {
+    ($name:ident $(-> $ret:ty)?) => {
+        $crate::TypedSel::<(), $crate::__typed_sel_ret!($($ret)?)>::new($crate::sel!($name))
+    };
+    ($($name:ident :)+ ($($arg:ty),* $(,)?) $(-> $ret:ty)?) => {
+        $crate::TypedSel::<($($arg,)*), $crate::__typed_sel_ret!($($ret)?)>::new(
+            $crate::sel!($($name:)+)
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __typed_sel_ret {
+    () => {
+        ()
+    };
+    ($ret:ty) => {
+        $ret
+    };
+}
+
 /// Sends a message to an object or class.
 ///
 /// The first argument can be any type that implements [`MessageReceiver`],
@@ -70,7 +198,15 @@ macro_rules! sel {
 ///
 /// The syntax is similar to the message syntax in Objective-C.
 ///
-/// Variadic arguments are not currently supported.
+/// Methods with a trailing variadic argument list (`...` in Objective-C)
+/// are sent by writing the variadic arguments after the last fixed argument,
+/// separated from it by a semicolon. Because C promotes variadic arguments,
+/// only types that are unaffected by this promotion can be passed there, see
+/// [`VariadicArgument`]; for example an `f32` must be converted to an `f64`
+/// first. Only the fixed arguments are checked by the `verify_message`
+/// feature.
+///
+/// [`VariadicArgument`]: crate::VariadicArgument
 ///
 /// [`MessageReceiver`]: crate::MessageReceiver
 /// [`rc::Id`]: crate::rc::Id
@@ -113,7 +249,9 @@ macro_rules! sel {
 /// If the receiver is a raw pointer the user must ensure that it is valid
 /// (aligned, dereferenceable, initialized and so on). Messages to `null`
 /// pointers are allowed (though discouraged), but only if the return type
-/// itself is a pointer.
+/// itself is a pointer. Use [`msg_send_opt!`] to not send the message at all
+/// if the receiver is `nil`, or [`msg_send_zeroed!`] to get a zeroed value
+/// back for other return types.
 ///
 /// Finally, the method must not (yet, see [RFC-2945]) throw an exception.
 ///
@@ -130,6 +268,25 @@ macro_rules! sel {
 /// let _: () = unsafe { msg_send![obj, setArg1: 1, arg2: 2] };
 /// ```
 ///
+/// Sending variadic messages:
+///
+/// ```no_run
+/// # use std::os::raw::c_int;
+/// # use std::ptr;
+/// # use objc2::{class, msg_send};
+/// # use objc2::runtime::Object;
+/// let format: *mut Object;
+/// # let format: *mut Object = 0 as *mut Object;
+/// let obj: *mut Object;
+/// # let obj: *mut Object = 0 as *mut Object;
+/// let string: *mut Object = unsafe {
+///     msg_send![class!(NSString), stringWithFormat: format; 42 as c_int, 1.5f64]
+/// };
+/// let array: *mut Object = unsafe {
+///     msg_send![class!(NSArray), arrayWithObjects: obj; obj, ptr::null_mut::<Object>()]
+/// };
+/// ```
+///
 /// [RFC-2945]: https://rust-lang.github.io/rfcs/2945-c-unwind-abi.html
 #[macro_export]
 macro_rules! msg_send {
@@ -169,4 +326,358 @@ macro_rules! msg_send {
         }
         result
     });
+    (super($obj:expr, $superclass:expr), $($name:ident : $arg:expr $(,)?)+ ; $($varg:expr),* $(,)?) => ({
+        let sel = $crate::sel!($($name:)+);
+        let result;
+        match $crate::MessageReceiver::send_super_message_variadic(
+            &$obj,
+            $superclass,
+            sel,
+            ($($arg,)+),
+            ($($varg,)*),
+        ) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+ ; $($varg:expr),* $(,)?) => ({
+        let sel = $crate::sel!($($name:)+);
+        let result;
+        match $crate::MessageReceiver::send_message_variadic(&$obj, sel, ($($arg,)+), ($($varg,)*)) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+}
+
+/// [`msg_send!`] that returns a [`Result`] instead of panicking.
+///
+/// The syntax is the same as [`msg_send!`], but the result is wrapped in a
+/// `Result<R, `[`SendError`]`>`, where the error contains either the
+/// [`VerificationError`] if the `verify_message` feature is enabled and the
+/// types of the message didn't match the method, or the exception if the
+/// `catch_all` feature is enabled and the method threw one.
+///
+/// [`SendError`]: crate::SendError
+/// [`VerificationError`]: crate::VerificationError
+///
+/// # Safety
+///
+/// Same as [`msg_send!`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::{class, try_msg_send};
+/// # use objc2::runtime::Object;
+/// let obj: *mut Object = unsafe { try_msg_send![class!(NSObject), new] }.unwrap();
+/// let result: Result<usize, _> = unsafe { try_msg_send![obj, hash] };
+/// match result {
+///     Ok(hash) => println!("hash: {}", hash),
+///     Err(err) => println!("failed sending message: {}", err),
+/// }
+/// ```
+#[macro_export]
+macro_rules! try_msg_send {
+    (super($obj:expr, $superclass:expr), $name:ident) => ({
+        let sel = $crate::sel!($name);
+        $crate::MessageReceiver::send_super_message(&$obj, $superclass, sel, ())
+    });
+    (super($obj:expr, $superclass:expr), $($name:ident : $arg:expr $(,)?)+) => ({
+        let sel = $crate::sel!($($name:)+);
+        $crate::MessageReceiver::send_super_message(&$obj, $superclass, sel, ($($arg,)+))
+    });
+    ($obj:expr, $name:ident) => ({
+        let sel = $crate::sel!($name);
+        $crate::MessageReceiver::send_message(&$obj, sel, ())
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+) => ({
+        let sel = $crate::sel!($($name:)+);
+        $crate::MessageReceiver::send_message(&$obj, sel, ($($arg,)+))
+    });
+    (super($obj:expr, $superclass:expr), $($name:ident : $arg:expr $(,)?)+ ; $($varg:expr),* $(,)?) => ({
+        let sel = $crate::sel!($($name:)+);
+        $crate::MessageReceiver::send_super_message_variadic(
+            &$obj,
+            $superclass,
+            sel,
+            ($($arg,)+),
+            ($($varg,)*),
+        )
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+ ; $($varg:expr),* $(,)?) => ({
+        let sel = $crate::sel!($($name:)+);
+        $crate::MessageReceiver::send_message_variadic(&$obj, sel, ($($arg,)+), ($($varg,)*))
+    });
+}
+
+/// [`msg_send!`] that doesn't send the message if the receiver is `nil`.
+///
+/// The result is wrapped in an [`Option`], which is [`None`] if the receiver
+/// was `nil` and [`Some`] with the return value of the method otherwise.
+/// This makes it easy to translate optional chaining, e.g. `[obj count]`
+/// where `obj` may be `nil`, without relying on what messages to `nil`
+/// return.
+///
+/// Since the message is never sent to `nil`, this also doesn't return an
+/// error for `nil` receivers when the `verify_message` feature is enabled.
+///
+/// # Panics
+///
+/// In the same cases as [`msg_send!`].
+///
+/// # Safety
+///
+/// Same as [`msg_send!`], except that the receiver is allowed to be `nil`
+/// regardless of the return type.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::msg_send_opt;
+/// # use objc2::runtime::Object;
+/// let obj: *mut Object;
+/// # obj = 0 as *mut Object;
+/// let count: Option<usize> = unsafe { msg_send_opt![obj, count] };
+/// let count = count.unwrap_or(0);
+/// ```
+#[macro_export]
+macro_rules! msg_send_opt {
+    ($obj:expr, $name:ident) => ({
+        let sel = $crate::sel!($name);
+        let result;
+        match $crate::MessageReceiver::send_message_opt(&$obj, sel, ()) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+) => ({
+        let sel = $crate::sel!($($name:)+);
+        let result;
+        match $crate::MessageReceiver::send_message_opt(&$obj, sel, ($($arg,)+)) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+}
+
+/// [`msg_send!`] that returns a zeroed value if the receiver is `nil`.
+///
+/// What a message to `nil` returns depends on the runtime and the return
+/// type; in particular, structs returned through memory may be left
+/// uninitialized. This macro checks for `nil` before sending the message, so
+/// that the result is all zeroes on every platform.
+///
+/// Since the message is never sent to `nil`, this also doesn't return an
+/// error for `nil` receivers when the `verify_message` feature is enabled.
+///
+/// # Panics
+///
+/// In the same cases as [`msg_send!`].
+///
+/// # Safety
+///
+/// Same as [`msg_send!`], except that the receiver is allowed to be `nil` as
+/// long as the return type is valid when all its bits are zero (e.g.
+/// numbers, raw pointers and structs of those, but not references).
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::{msg_send_zeroed, Encode, Encoding};
+/// # use objc2::runtime::Object;
+/// #[repr(C)]
+/// struct NSRange {
+///     location: usize,
+///     length: usize,
+/// }
+/// # unsafe impl Encode for NSRange {
+/// #     const ENCODING: Encoding<'static> = Encoding::Struct("_NSRange", &[usize::ENCODING, usize::ENCODING]);
+/// # }
+///
+/// let string: *mut Object;
+/// # string = 0 as *mut Object;
+/// let range: NSRange = unsafe { msg_send_zeroed![string, rangeOfString: string] };
+/// ```
+#[macro_export]
+macro_rules! msg_send_zeroed {
+    ($obj:expr, $name:ident) => ({
+        let sel = $crate::sel!($name);
+        let result;
+        match $crate::MessageReceiver::send_message_zeroed(&$obj, sel, ()) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+) => ({
+        let sel = $crate::sel!($($name:)+);
+        let result;
+        match $crate::MessageReceiver::send_message_zeroed(&$obj, sel, ($($arg,)+)) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    });
+}
+
+/// [`msg_send!`] for methods returning objects, which are returned as an
+/// [`Option<Id<T, O>>`][`rc::Id`] with the correct retain count.
+///
+/// Whether the method returns a retained object or not is determined at
+/// compile-time from the selector, following the [method family] rules that
+/// ARC uses:
+///
+/// - Selectors in the `new`, `copy` and `mutableCopy` families are expected
+///   to return a retained object, which is wrapped with [`Id::new`].
+/// - Selectors in the `alloc` family return an `Option<`[`Allocated<T>`]`>`,
+///   since the object can't be used before it has been initialized.
+/// - Selectors in the `init` family must be sent to an
+///   `Option<Allocated<T>>`, which is consumed, since `init` methods take
+///   ownership of their receiver. If the receiver is `None`, the message is
+///   not sent and `None` is returned.
+/// - Any other selector is expected to return an unretained object, which is
+///   retained with [`Id::retain`].
+///
+/// Methods that don't follow these rules (e.g. are annotated with
+/// `ns_returns_retained`) must be sent with [`msg_send!`] instead.
+///
+/// [method family]: https://clang.llvm.org/docs/AutomaticReferenceCounting.html#method-families
+/// [`rc::Id`]: crate::rc::Id
+/// [`Id::new`]: crate::rc::Id::new
+/// [`Id::retain`]: crate::rc::Id::retain
+/// [`Allocated<T>`]: crate::rc::Allocated
+///
+/// # Panics
+///
+/// In the same cases as [`msg_send!`].
+///
+/// # Safety
+///
+/// Same as [`msg_send!`], with the added requirement that the method must
+/// return an object (or `nil`) of type `T`, and that the ownership `O` must
+/// be correct, see [`Id::new`] and [`Id::retain`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use objc2::{class, msg_send_id};
+/// # use objc2::runtime::Object;
+/// # use objc2::rc::{Id, Owned, Shared};
+/// let cls = class!(NSObject);
+/// let obj: Option<Id<Object, Owned>> = unsafe { msg_send_id![cls, new] };
+/// let obj: Option<Id<Object, Owned>> = unsafe {
+///     msg_send_id![msg_send_id![cls, alloc], init]
+/// };
+/// let obj: Id<Object, Shared> = obj.unwrap().into();
+/// let description: Option<Id<Object, Shared>> = unsafe {
+///     msg_send_id![&obj, description]
+/// };
+/// ```
+#[macro_export]
+macro_rules! msg_send_id {
+    ($obj:expr, $name:ident) => ({
+        const NAME: &[u8] = stringify!($name).as_bytes();
+        $crate::__msg_send_id_helper!(NAME, $obj, $crate::sel!($name), ())
+    });
+    ($obj:expr, $($name:ident : $arg:expr $(,)?)+) => ({
+        const NAME: &[u8] = concat!($(stringify!($name), ':'),+).as_bytes();
+        $crate::__msg_send_id_helper!(NAME, $obj, $crate::sel!($($name:)+), ($($arg,)+))
+    });
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __msg_send_id_helper {
+    ($name:ident, $obj:expr, $sel:expr, $args:expr) => {{
+        use $crate::__macro_helpers::{in_selector_family, MsgSendId, RetainSemantics};
+        const NEW: bool = in_selector_family($name, b"new");
+        const ALLOC: bool = in_selector_family($name, b"alloc");
+        const INIT: bool = in_selector_family($name, b"init");
+        const COPY: bool =
+            in_selector_family($name, b"copy") || in_selector_family($name, b"mutableCopy");
+        let sel = $sel;
+        let result;
+        match <RetainSemantics<NEW, ALLOC, INIT, COPY> as MsgSendId<_, _>>::send_message_id(
+            $obj, sel, $args,
+        ) {
+            Err(s) => panic!("{}", s),
+            Ok(r) => result = r,
+        }
+        result
+    }};
+}
+
+/// [`msg_send!`] for methods that report errors through an `NSError **`
+/// out-parameter, which is converted to a [`Result`].
+///
+/// The out-parameter is written as `_` in place of the last argument, e.g.
+/// `msg_send_err![obj, writeToFile: path, options: 0usize, error: _]` sends
+/// `writeToFile:options:error:`, and allocates and passes the pointer that
+/// the error is written to. Arguments must be separated by commas.
+///
+/// Following Cocoa's conventions, the error is only looked at if the return
+/// value signals failure, that is, if the method returned `NO` or `nil`. The
+/// successful result can be:
+///
+/// - `()`, for methods returning `BOOL`.
+/// - `NonNull<T>` or `&T`, for methods returning an object.
+///
+/// The error type is usually `NSError` from `objc2-foundation`, and is
+/// retained and returned as an [`Option<Id<E, Shared>>`][`rc::Id`], which is
+/// [`None`] if the method failed without returning an error (which some
+/// methods do, even though it goes against the conventions).
+///
+/// [`rc::Id`]: crate::rc::Id
+///
+/// # Panics
+///
+/// In the same cases as [`msg_send!`].
+///
+/// # Safety
+///
+/// Same as [`msg_send!`], with the added requirement that the last argument
+/// of the method must be an `NSError **` (or another object type `E`).
+///
+/// # Examples
+///
+/// ```no_run
+/// # use core::ptr::NonNull;
+/// # use objc2::{class, msg_send_err};
+/// # use objc2::rc::{Id, Shared};
+/// # use objc2::runtime::Object;
+/// # type NSError = Object;
+/// let manager: &Object;
+/// # manager = unsafe { objc2::msg_send![class!(NSFileManager), defaultManager] };
+/// let path: &Object;
+/// # path = manager;
+/// let result: Result<(), Option<Id<NSError, Shared>>> = unsafe {
+///     msg_send_err![manager, removeItemAtPath: path, error: _]
+/// };
+/// let result: Result<NonNull<Object>, Option<Id<NSError, Shared>>> = unsafe {
+///     msg_send_err![class!(NSData), dataWithContentsOfFile: path, options: 0usize, error: _]
+/// };
+/// ```
+#[macro_export]
+macro_rules! msg_send_err {
+    ($obj:expr, $($rest:tt)+) => {
+        $crate::__msg_send_err!($obj; (); $($rest)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __msg_send_err {
+    ($obj:expr; ($($name:ident : $arg:expr,)*); $err:ident : _ $(,)?) => ({
+        let mut error = ::core::ptr::null_mut();
+        let raw = $crate::msg_send![$obj, $($name: $arg,)* $err: &mut error];
+        $crate::__macro_helpers::error_result(raw, error)
+    });
+    ($obj:expr; ($($acc:tt)*); $name:ident : $arg:expr, $($rest:tt)+) => {
+        $crate::__msg_send_err!($obj; ($($acc)* $name: $arg,); $($rest)+)
+    };
 }
@@ -1,4 +1,7 @@
-use super::{conditional_try, Encode, MessageArguments, MessageError};
+use super::{
+    conditional_try, Encode, MessageArguments, SendError, VariadicArguments,
+    VariadicMessageArguments,
+};
 use crate::ffi;
 use crate::runtime::{Class, Imp, Object, Sel};
 
@@ -27,7 +30,7 @@ pub(crate) unsafe fn send_unverified<A, R>(
     receiver: *mut Object,
     sel: Sel,
     args: A,
-) -> Result<R, MessageError>
+) -> Result<R, SendError>
 where
     A: MessageArguments,
     R: Encode,
@@ -42,7 +45,7 @@ pub(crate) unsafe fn send_super_unverified<A, R>(
     superclass: &Class,
     sel: Sel,
     args: A,
-) -> Result<R, MessageError>
+) -> Result<R, SendError>
 where
     A: MessageArguments,
     R: Encode,
@@ -55,3 +58,41 @@ where
     let msg_send_fn = R::MSG_SEND_SUPER;
     unsafe { conditional_try(|| A::__invoke(msg_send_fn, receiver, sel, args)) }
 }
+
+#[inline(always)]
+pub(crate) unsafe fn send_variadic_unverified<A, V, R>(
+    receiver: *mut Object,
+    sel: Sel,
+    args: A,
+    vargs: V,
+) -> Result<R, SendError>
+where
+    A: VariadicMessageArguments,
+    V: VariadicArguments,
+    R: Encode,
+{
+    let msg_send_fn = R::MSG_SEND;
+    unsafe { conditional_try(|| A::__invoke_variadic(msg_send_fn, receiver, sel, args, vargs)) }
+}
+
+#[inline]
+pub(crate) unsafe fn send_super_variadic_unverified<A, V, R>(
+    receiver: *mut Object,
+    superclass: &Class,
+    sel: Sel,
+    args: A,
+    vargs: V,
+) -> Result<R, SendError>
+where
+    A: VariadicMessageArguments,
+    V: VariadicArguments,
+    R: Encode,
+{
+    let sup = ffi::objc_super {
+        receiver: receiver as *mut _,
+        super_class: superclass as *const Class as *const _,
+    };
+    let receiver = &sup as *const ffi::objc_super as *mut Object;
+    let msg_send_fn = R::MSG_SEND_SUPER;
+    unsafe { conditional_try(|| A::__invoke_variadic(msg_send_fn, receiver, sel, args, vargs)) }
+}
@@ -1,14 +1,17 @@
 use core::mem;
 
-use super::{conditional_try, Encode, MessageArguments, MessageError};
+use super::{
+    conditional_try, Encode, MessageArguments, SendError, VariadicArguments,
+    VariadicMessageArguments,
+};
 use crate::ffi;
-use crate::runtime::{Class, Object, Sel};
+use crate::runtime::{Bool, Class, Object, Sel};
 
 pub(crate) unsafe fn send_unverified<A, R>(
     receiver: *mut Object,
     sel: Sel,
     args: A,
-) -> Result<R, MessageError>
+) -> Result<R, SendError>
 where
     A: MessageArguments,
     R: Encode,
@@ -34,7 +37,7 @@ pub(crate) unsafe fn send_super_unverified<A, R>(
     superclass: &Class,
     sel: Sel,
     args: A,
-) -> Result<R, MessageError>
+) -> Result<R, SendError>
 where
     A: MessageArguments,
     R: Encode,
@@ -53,3 +56,81 @@ where
     let msg_send_fn = msg_send_fn.expect("Null IMP");
     unsafe { conditional_try(|| A::__invoke(msg_send_fn, receiver, sel, args)) }
 }
+
+pub(crate) unsafe fn send_variadic_unverified<A, V, R>(
+    receiver: *mut Object,
+    sel: Sel,
+    args: A,
+    vargs: V,
+) -> Result<R, SendError>
+where
+    A: VariadicMessageArguments,
+    V: VariadicArguments,
+    R: Encode,
+{
+    if receiver.is_null() {
+        // SAFETY: Same as in `send_unverified`.
+        return unsafe { mem::zeroed() };
+    }
+
+    let sel_ptr = sel.as_ptr() as *const _;
+    let msg_send_fn = unsafe { ffi::objc_msg_lookup(receiver as *mut _, sel_ptr) };
+    let msg_send_fn = msg_send_fn.expect("Null IMP");
+    unsafe { conditional_try(|| A::__invoke_variadic(msg_send_fn, receiver, sel, args, vargs)) }
+}
+
+pub(crate) unsafe fn send_super_variadic_unverified<A, V, R>(
+    receiver: *mut Object,
+    superclass: &Class,
+    sel: Sel,
+    args: A,
+    vargs: V,
+) -> Result<R, SendError>
+where
+    A: VariadicMessageArguments,
+    V: VariadicArguments,
+    R: Encode,
+{
+    if receiver.is_null() {
+        // SAFETY: Same as in `send_unverified`.
+        return unsafe { mem::zeroed() };
+    }
+
+    let sup = ffi::objc_super {
+        receiver: receiver as *mut _,
+        super_class: superclass as *const Class as *const _,
+    };
+    let sel_ptr = sel.as_ptr() as *const _;
+    let msg_send_fn = unsafe { ffi::objc_msg_lookup_super(&sup, sel_ptr) };
+    let msg_send_fn = msg_send_fn.expect("Null IMP");
+    unsafe { conditional_try(|| A::__invoke_variadic(msg_send_fn, receiver, sel, args, vargs)) }
+}
+
+/// Give the class a chance to add the method with `+resolveInstanceMethod:`
+/// or `+resolveClassMethod:`, like the runtime does before forwarding the
+/// message.
+///
+/// Apple's `class_getInstanceMethod` already does this, but GNUStep's
+/// doesn't.
+///
+/// Returns whether the method was added.
+pub(crate) fn resolve_method(cls: &Class, sel: Sel) -> bool {
+    let (receiver, resolver) = if cls.is_metaclass() {
+        // The metaclass has the same name as its class
+        match Class::get(cls.name()) {
+            Some(receiver) => (receiver, sel!(resolveClassMethod:)),
+            None => return false,
+        }
+    } else {
+        (cls, sel!(resolveInstanceMethod:))
+    };
+    // Root classes other than `NSObject` may not implement the resolvers
+    if receiver.class_method(resolver).is_none() {
+        return false;
+    }
+    let receiver = receiver as *const Class as *mut Object;
+    // SAFETY: The resolver exists, and has this signature. This is sent
+    // unverified to avoid recursing.
+    let resolved: Result<Bool, _> = unsafe { send_unverified(receiver, resolver, (sel,)) };
+    matches!(resolved, Ok(resolved) if resolved.is_true())
+}
@@ -1,32 +1,43 @@
-use alloc::string::String;
 use core::fmt;
 use core::mem;
 use core::mem::ManuallyDrop;
 use core::ptr::NonNull;
 use std::error::Error;
 
+#[cfg(feature = "catch_all")]
+use crate::rc::Shared;
 use crate::rc::{Id, Ownership};
-use crate::runtime::{Class, Imp, Object, Sel};
+use crate::runtime::{Class, Imp, Method, Object, Sel};
 use crate::{Encode, EncodeArguments, RefEncode};
 
 #[cfg(feature = "catch_all")]
-unsafe fn conditional_try<R: Encode>(f: impl FnOnce() -> R) -> Result<R, MessageError> {
-    use alloc::borrow::ToOwned;
-    unsafe { crate::exception::catch(f) }.map_err(|exception| {
-        if let Some(exception) = exception {
-            MessageError(alloc::format!("Uncaught exception {:?}", exception))
-        } else {
-            MessageError("Uncaught exception nil".to_owned())
-        }
-    })
+unsafe fn conditional_try<R: Encode>(f: impl FnOnce() -> R) -> Result<R, SendError> {
+    unsafe { crate::exception::catch(f) }.map_err(SendError::Exception)
 }
 
 #[cfg(not(feature = "catch_all"))]
 #[inline(always)]
-unsafe fn conditional_try<R: Encode>(f: impl FnOnce() -> R) -> Result<R, MessageError> {
+unsafe fn conditional_try<R: Encode>(f: impl FnOnce() -> R) -> Result<R, SendError> {
     Ok(f())
 }
 
+#[cfg(feature = "trace_message")]
+use crate::trace::trace;
+
+#[cfg(not(feature = "trace_message"))]
+#[inline(always)]
+#[allow(clippy::extra_unused_type_parameters)]
+unsafe fn trace<A, R>(
+    _receiver: *mut Object,
+    _superclass: Option<&Class>,
+    _sel: Sel,
+    send: impl FnOnce() -> Result<R, SendError>,
+) -> Result<R, SendError> {
+    send()
+}
+
+mod typed;
+mod variadic;
 #[cfg(feature = "malloc")]
 mod verify;
 
@@ -37,9 +48,32 @@ mod platform;
 #[path = "gnustep.rs"]
 mod platform;
 
-use self::platform::{send_super_unverified, send_unverified};
+use self::platform::{
+    send_super_unverified, send_super_variadic_unverified, send_unverified,
+    send_variadic_unverified,
+};
+pub use self::typed::{CachedImp, TypedSel};
+pub use self::variadic::{VariadicArgument, VariadicArguments, VariadicMessageArguments};
 #[cfg(feature = "malloc")]
-use self::verify::{verify_message_signature, VerificationError};
+use self::verify::verify_message_signature;
+#[cfg(feature = "verify_message")]
+use self::verify::verify_super_message_signature;
+#[cfg(feature = "malloc")]
+pub use self::verify::VerificationError;
+#[cfg(feature = "malloc")]
+pub(crate) use self::verify::{forget_verified, forget_verified_method};
+
+/// Looks up the instance method of the class for the selector, giving the
+/// class a chance to add it dynamically with `+resolveInstanceMethod:` (or
+/// `+resolveClassMethod:` for metaclasses) if it doesn't exist.
+pub(crate) fn lookup_method(cls: &Class, sel: Sel) -> Option<&Method> {
+    let method = cls.instance_method(sel);
+    #[cfg(gnustep)]
+    if method.is_none() && platform::resolve_method(cls, sel) {
+        return cls.instance_method(sel);
+    }
+    method
+}
 
 /// Types that can be sent Objective-C messages.
 ///
@@ -80,6 +114,8 @@ pub(crate) mod private {
     impl<'a, T: Message + ?Sized> Sealed for &'a mut T {}
     impl<T: Message + ?Sized> Sealed for NonNull<T> {}
     impl<T: Message + ?Sized, O: Ownership> Sealed for Id<T, O> {}
+    impl<T: Message + ?Sized, O: Ownership> Sealed for &Id<T, O> {}
+    impl<T: Message + ?Sized, O: Ownership> Sealed for &mut Id<T, O> {}
 
     impl<T: MessageReceiver + ?Sized> Sealed for ManuallyDrop<T> {}
 }
@@ -118,7 +154,7 @@ pub unsafe trait MessageReceiver: private::Sealed {
     /// The added invariant is that the selector must take the same number of
     /// arguments as is given.
     #[cfg_attr(not(feature = "verify_message"), inline(always))]
-    unsafe fn send_message<A, R>(&self, sel: Sel, args: A) -> Result<R, MessageError>
+    unsafe fn send_message<A, R>(&self, sel: Sel, args: A) -> Result<R, SendError>
     where
         A: MessageArguments,
         R: Encode,
@@ -131,12 +167,12 @@ pub unsafe trait MessageReceiver: private::Sealed {
             let cls = if let Some(this) = this {
                 this.class()
             } else {
-                return Err(VerificationError::NilReceiver(sel).into());
+                return Err(VerificationError::NilReceiver { sel }.into());
             };
 
             verify_message_signature::<A, R>(cls, sel)?;
         }
-        unsafe { send_unverified(this, sel, args) }
+        unsafe { trace::<A, R>(this, None, sel, || send_unverified(this, sel, args)) }
     }
 
     /// Sends a message to self's superclass with the given selector and
@@ -164,7 +200,7 @@ pub unsafe trait MessageReceiver: private::Sealed {
         superclass: &Class,
         sel: Sel,
         args: A,
-    ) -> Result<R, MessageError>
+    ) -> Result<R, SendError>
     where
         A: MessageArguments,
         R: Encode,
@@ -172,19 +208,181 @@ pub unsafe trait MessageReceiver: private::Sealed {
         let this = self.as_raw_receiver();
         #[cfg(feature = "verify_message")]
         {
-            if this.is_null() {
-                return Err(VerificationError::NilReceiver(sel).into());
-            }
-            verify_message_signature::<A, R>(superclass, sel)?;
+            // SAFETY: Caller ensures only valid or NULL pointers.
+            let this = unsafe { this.as_ref() };
+            let cls = if let Some(this) = this {
+                this.class()
+            } else {
+                return Err(VerificationError::NilReceiver { sel }.into());
+            };
+
+            verify_super_message_signature::<A, R>(cls, superclass, sel)?;
+        }
+        unsafe {
+            trace::<A, R>(this, Some(superclass), sel, || {
+                send_super_unverified(this, superclass, sel, args)
+            })
+        }
+    }
+
+    /// Sends a message to self if it is not `nil`.
+    ///
+    /// If self is `nil`, the message is not sent and `None` is returned,
+    /// which is similar to optional chaining in Swift. Otherwise this is the
+    /// same as [`MessageReceiver::send_message`].
+    ///
+    /// If the selector is known at compile-time, it is recommended to use the
+    /// [`msg_send_opt!`][`crate::msg_send_opt`] macro rather than this method.
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`MessageReceiver::send_message`], except that self is allowed to be
+    /// `nil` regardless of the return type.
+    #[inline]
+    unsafe fn send_message_opt<A, R>(&self, sel: Sel, args: A) -> Result<Option<R>, SendError>
+    where
+        A: MessageArguments,
+        R: Encode,
+    {
+        if self.as_raw_receiver().is_null() {
+            return Ok(None);
+        }
+        unsafe { self.send_message(sel, args) }.map(Some)
+    }
+
+    /// Sends a message to self, returning a zeroed value if self is `nil`.
+    ///
+    /// The runtimes don't agree on what messages to `nil` return for types
+    /// other than pointers and integers: struct returns may be left
+    /// uninitialized, depending on the platform and the size of the struct.
+    /// This checks for `nil` before sending the message instead, so that the
+    /// result is all zeroes on every platform, like the nil-checks clang
+    /// inserts for such messages.
+    ///
+    /// If the selector is known at compile-time, it is recommended to use the
+    /// [`msg_send_zeroed!`][`crate::msg_send_zeroed`] macro rather than this
+    /// method.
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`MessageReceiver::send_message`], except that self is allowed to be
+    /// `nil` as long as the return type is valid when all its bits are zero
+    /// (e.g. numbers, raw pointers, [`Bool`][`crate::runtime::Bool`] and
+    /// structs of those, but not references).
+    #[inline]
+    unsafe fn send_message_zeroed<A, R>(&self, sel: Sel, args: A) -> Result<R, SendError>
+    where
+        A: MessageArguments,
+        R: Encode,
+    {
+        if self.as_raw_receiver().is_null() {
+            // SAFETY: Upheld by the caller.
+            return Ok(unsafe { mem::zeroed() });
+        }
+        unsafe { self.send_message(sel, args) }
+    }
+
+    /// Sends a message with a trailing variadic argument list to self.
+    ///
+    /// `args` are the fixed arguments of the method, and `vargs` the
+    /// arguments in the variadic part of it, e.g. the values used by the
+    /// format string of `+[NSString stringWithFormat:]`.
+    ///
+    /// If the selector is known at compile-time, it is recommended to use the
+    /// [`msg_send!`][`crate::msg_send`] macro rather than this method.
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`MessageReceiver::send_message`]. Additionally, the method must
+    /// accept the given variadic arguments, e.g. they must match the format
+    /// string or be terminated by `nil` if the method requires that.
+    ///
+    /// Only the fixed arguments are checked when the `verify_message`
+    /// feature is enabled, since the types of variadic arguments are not
+    /// part of the method's encoding.
+    #[cfg_attr(not(feature = "verify_message"), inline(always))]
+    unsafe fn send_message_variadic<A, V, R>(
+        &self,
+        sel: Sel,
+        args: A,
+        vargs: V,
+    ) -> Result<R, SendError>
+    where
+        A: VariadicMessageArguments,
+        V: VariadicArguments,
+        R: Encode,
+    {
+        let this = self.as_raw_receiver();
+        #[cfg(feature = "verify_message")]
+        {
+            // SAFETY: Caller ensures only valid or NULL pointers.
+            let this = unsafe { this.as_ref() };
+            let cls = if let Some(this) = this {
+                this.class()
+            } else {
+                return Err(VerificationError::NilReceiver { sel }.into());
+            };
+
+            verify_message_signature::<A, R>(cls, sel)?;
+        }
+        unsafe {
+            trace::<A, R>(this, None, sel, || {
+                send_variadic_unverified(this, sel, args, vargs)
+            })
+        }
+    }
+
+    /// Sends a message with a trailing variadic argument list to self's
+    /// superclass.
+    ///
+    /// See [`MessageReceiver::send_message_variadic`] and
+    /// [`MessageReceiver::send_super_message`].
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`MessageReceiver::send_message_variadic`].
+    #[cfg_attr(not(feature = "verify_message"), inline(always))]
+    unsafe fn send_super_message_variadic<A, V, R>(
+        &self,
+        superclass: &Class,
+        sel: Sel,
+        args: A,
+        vargs: V,
+    ) -> Result<R, SendError>
+    where
+        A: VariadicMessageArguments,
+        V: VariadicArguments,
+        R: Encode,
+    {
+        let this = self.as_raw_receiver();
+        #[cfg(feature = "verify_message")]
+        {
+            // SAFETY: Caller ensures only valid or NULL pointers.
+            let this = unsafe { this.as_ref() };
+            let cls = if let Some(this) = this {
+                this.class()
+            } else {
+                return Err(VerificationError::NilReceiver { sel }.into());
+            };
+
+            verify_super_message_signature::<A, R>(cls, superclass, sel)?;
+        }
+        unsafe {
+            trace::<A, R>(this, Some(superclass), sel, || {
+                send_super_variadic_unverified(this, superclass, sel, args, vargs)
+            })
         }
-        unsafe { send_super_unverified(this, superclass, sel, args) }
     }
 
     /// Verify that the argument and return types match the encoding of the
     /// method for the given selector.
     ///
     /// This will look up the encoding of the method for the given selector,
-    /// `sel`, and return a [`MessageError`] if any encodings differ for the
+    /// `sel`, and return a [`SendError`] if any encodings differ for the
     /// arguments `A` and return type `R`.
     ///
     /// # Example
@@ -200,13 +398,13 @@ pub unsafe trait MessageReceiver: private::Sealed {
     /// assert!(result.is_ok());
     /// ```
     #[cfg(feature = "malloc")]
-    fn verify_message<A, R>(&self, sel: Sel) -> Result<(), MessageError>
+    fn verify_message<A, R>(&self, sel: Sel) -> Result<(), SendError>
     where
         A: EncodeArguments,
         R: Encode,
     {
         let obj = unsafe { &*self.as_raw_receiver() };
-        verify_message_signature::<A, R>(obj.class(), sel).map_err(MessageError::from)
+        verify_message_signature::<A, R>(obj.class(), sel).map_err(SendError::from)
     }
 }
 
@@ -256,6 +454,20 @@ unsafe impl<T: Message + ?Sized, O: Ownership> MessageReceiver for Id<T, O> {
     }
 }
 
+unsafe impl<T: Message + ?Sized, O: Ownership> MessageReceiver for &Id<T, O> {
+    #[inline]
+    fn as_raw_receiver(&self) -> *mut Object {
+        (**self).as_raw_receiver()
+    }
+}
+
+unsafe impl<T: Message + ?Sized, O: Ownership> MessageReceiver for &mut Id<T, O> {
+    #[inline]
+    fn as_raw_receiver(&self) -> *mut Object {
+        (**self).as_raw_receiver()
+    }
+}
+
 unsafe impl<T: MessageReceiver + ?Sized> MessageReceiver for ManuallyDrop<T> {
     #[inline]
     fn as_raw_receiver(&self) -> *mut Object {
@@ -345,26 +557,57 @@ message_args_impl!(
 ///   enabled
 /// - the encodings of the arguments do not match the encoding of the method
 ///   and the `verify_message` feature is enabled
+///
+/// If neither of these features are enabled, this type can't be
+/// constructed.
 #[derive(Debug)]
-pub struct MessageError(String);
+#[non_exhaustive]
+pub enum SendError {
+    /// The types of the message didn't match the method's.
+    #[cfg(feature = "malloc")]
+    Verification(VerificationError),
+    /// The method threw an exception, which was caught.
+    #[cfg(feature = "catch_all")]
+    Exception(Option<Id<Object, Shared>>),
+}
+
+/// The previous name of [`SendError`].
+pub type MessageError = SendError;
 
-impl fmt::Display for MessageError {
+impl fmt::Display for SendError {
+    #[cfg_attr(
+        not(any(feature = "malloc", feature = "catch_all")),
+        allow(unused_variables)
+    )]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
+        match *self {
+            #[cfg(feature = "malloc")]
+            Self::Verification(ref err) => fmt::Display::fmt(err, f),
+            #[cfg(feature = "catch_all")]
+            Self::Exception(Some(ref exception)) => {
+                write!(f, "Uncaught exception {:?}", exception)
+            }
+            #[cfg(feature = "catch_all")]
+            Self::Exception(None) => write!(f, "Uncaught exception nil"),
+        }
     }
 }
 
-impl Error for MessageError {
-    fn description(&self) -> &str {
-        &self.0
+impl Error for SendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            #[cfg(feature = "malloc")]
+            Self::Verification(ref err) => Some(err),
+            #[cfg(feature = "catch_all")]
+            Self::Exception(_) => None,
+        }
     }
 }
 
 #[cfg(feature = "malloc")]
-impl<'a> From<VerificationError<'a>> for MessageError {
-    fn from(err: VerificationError<'_>) -> MessageError {
-        use alloc::string::ToString;
-        MessageError(err.to_string())
+impl From<VerificationError> for SendError {
+    fn from(err: VerificationError) -> SendError {
+        SendError::Verification(err)
     }
 }
 
@@ -422,6 +665,42 @@ mod tests {
         assert!(result.is_null());
     }
 
+    #[test]
+    fn test_send_message_opt() {
+        let obj = test_utils::custom_object();
+        let result: Option<u32> = unsafe {
+            let _: () = msg_send![obj, setFoo: 4u32];
+            msg_send_opt![obj, foo]
+        };
+        assert_eq!(result, Some(4));
+
+        // Not sent, so this doesn't fail verification either
+        let nil: *mut Object = ::core::ptr::null_mut();
+        let result: Option<u32> = unsafe { msg_send_opt![nil, foo] };
+        assert_eq!(result, None);
+        let result: Option<()> = unsafe { msg_send_opt![nil, setFoo: 4u32] };
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_send_message_zeroed() {
+        let obj = test_utils::custom_object();
+        let result: test_utils::CustomStruct = unsafe { msg_send_zeroed![obj, customStruct] };
+        assert_eq!(result.a, 1);
+
+        let nil: *mut Object = ::core::ptr::null_mut();
+        let result: test_utils::CustomStruct = unsafe { msg_send_zeroed![nil, customStruct] };
+        let expected = test_utils::CustomStruct {
+            a: 0,
+            b: 0,
+            c: 0,
+            d: 0,
+        };
+        assert_eq!(result, expected);
+        let result: f64 = unsafe { msg_send_zeroed![nil, doubleValue] };
+        assert_eq!(result, 0.0);
+    }
+
     #[test]
     fn test_send_message_super() {
         let obj = test_utils::custom_subclass_object();
@@ -434,6 +713,11 @@ mod tests {
             // The subclass is overriden to return foo + 2
             let foo: u32 = msg_send![obj, foo];
             assert_eq!(foo, 6);
+
+            // Class methods are sent to the superclass' metaclass
+            let subclass = test_utils::custom_subclass();
+            let foo: u32 = msg_send![super(subclass, superclass.metaclass()), classFoo];
+            assert_eq!(foo, 7);
         }
     }
 
@@ -452,6 +736,37 @@ mod tests {
         assert_eq!(result, 4);
     }
 
+    #[test]
+    fn test_try_msg_send() {
+        let obj = test_utils::custom_object();
+        let result: Result<(), SendError> = unsafe { try_msg_send![obj, setFoo: 4u32] };
+        assert!(result.is_ok());
+        let result: Result<u32, SendError> = unsafe { try_msg_send![obj, foo] };
+        assert_eq!(result.unwrap(), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "verify_message")]
+    fn test_try_msg_send_verification_error() {
+        let obj = test_utils::custom_object();
+        let result: Result<u64, SendError> = unsafe { try_msg_send![obj, foo] };
+        match result {
+            Err(SendError::Verification(VerificationError::MismatchedReturn { sel, .. })) => {
+                assert_eq!(sel, sel!(foo));
+            }
+            _ => panic!("expected mismatched return type"),
+        }
+
+        let nil: *mut Object = core::ptr::null_mut();
+        let result: Result<u32, SendError> = unsafe { try_msg_send![nil, foo] };
+        match result {
+            Err(SendError::Verification(VerificationError::NilReceiver { sel })) => {
+                assert_eq!(sel, sel!(foo));
+            }
+            _ => panic!("expected nil receiver"),
+        }
+    }
+
     #[test]
     #[cfg(feature = "malloc")]
     fn test_verify_message() {
@@ -462,6 +777,13 @@ mod tests {
         // Incorrect types
         assert!(obj.verify_message::<(), u64>(sel!(setFoo:)).is_err());
         // Unimplemented selector
-        assert!(obj.verify_message::<(u32,), ()>(sel!(setFoo)).is_err());
+        let err = obj.verify_message::<(u32,), ()>(sel!(setFoo)).unwrap_err();
+        match err {
+            SendError::Verification(VerificationError::MethodNotFound { cls, sel }) => {
+                assert_eq!(cls, test_utils::custom_class());
+                assert_eq!(sel, sel!(setFoo));
+            }
+            _ => panic!("expected method not found"),
+        }
     }
 }
@@ -0,0 +1,432 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use super::{conditional_try, MessageArguments, MessageReceiver};
+use crate::runtime::{Class, Imp, Sel};
+use crate::Encode;
+
+/// A selector that carries the argument types `A` and the return type `R`
+/// of the method it names.
+///
+/// This allows stating the signature of a method once, and then sending it
+/// without repeating the types at each call site. The signature can also be
+/// checked against a class with [`TypedSel::verify`].
+///
+/// Usually constructed with the [`typed_sel!`][crate::typed_sel] macro.
+///
+/// # Example
+///
+/// ```no_run
+/// use objc2::runtime::Object;
+/// use objc2::{class, msg_send, typed_sel, TypedSel};
+///
+/// fn object_at_index() -> TypedSel<(usize,), *mut Object> {
+///     typed_sel!(objectAtIndex: (usize) -> *mut Object)
+/// }
+///
+/// let array: *mut Object = unsafe { msg_send![class!(NSArray), new] };
+/// # #[cfg(feature = "malloc")]
+/// assert!(object_at_index().verify(class!(NSArray)).is_ok());
+/// let count = typed_sel!(count -> usize);
+/// if unsafe { count.send(&array, ()) } > 0 {
+///     let first = unsafe { object_at_index().send(&array, (0,)) };
+/// }
+/// ```
+pub struct TypedSel<A, R> {
+    sel: Sel,
+    p: PhantomData<fn(A) -> R>,
+}
+
+impl<A: MessageArguments, R: Encode> TypedSel<A, R> {
+    /// Constructs a [`TypedSel`] from the given selector.
+    ///
+    /// # Panics
+    ///
+    /// If the selector and the arguments `A` have a different number of
+    /// arguments.
+    pub fn new(sel: Sel) -> Self {
+        let sel_args = sel.name().chars().filter(|&c| c == ':').count();
+        assert_eq!(
+            sel_args,
+            A::ENCODINGS.len(),
+            "Selector {:?} accepts {} arguments, but {} were specified",
+            sel,
+            sel_args,
+            A::ENCODINGS.len(),
+        );
+        Self {
+            sel,
+            p: PhantomData,
+        }
+    }
+
+    /// The untyped selector.
+    #[inline]
+    pub fn sel(self) -> Sel {
+        self.sel
+    }
+
+    /// Verify that the argument and return types match the encoding of the
+    /// instance method of the given class.
+    ///
+    /// See [`MessageReceiver::verify_message`].
+    #[cfg(feature = "malloc")]
+    pub fn verify(self, cls: &Class) -> Result<(), super::SendError> {
+        super::verify_message_signature::<A, R>(cls, self.sel).map_err(Into::into)
+    }
+
+    /// Looks up the implementation of the method on the given class, so
+    /// that it can be called directly afterwards, without going through the
+    /// dynamic dispatch of `objc_msgSend` on every call.
+    ///
+    /// Returns [`None`] if the class (and its superclasses) doesn't have an
+    /// instance method for the selector, even after trying to resolve it
+    /// dynamically with `+resolveInstanceMethod:`. Use the metaclass to look
+    /// up class methods.
+    ///
+    /// See [`CachedImp`] for when the implementation may be used.
+    ///
+    /// # Panics
+    ///
+    /// If the `verify_message` feature is enabled and the types don't match
+    /// the method's.
+    pub fn imp(self, cls: &Class) -> Option<CachedImp<A, R>> {
+        let method = super::lookup_method(cls, self.sel)?;
+        #[cfg(feature = "verify_message")]
+        if let Err(err) = self.verify(cls) {
+            panic!("{}", err);
+        }
+        // SAFETY: Classes are never deallocated once registered.
+        let cls: &'static Class = unsafe { &*(cls as *const Class) };
+        Some(CachedImp {
+            imp: method.implementation(),
+            cls,
+            sel: self.sel,
+            p: PhantomData,
+        })
+    }
+
+    /// Sends the message to the receiver with the given arguments.
+    ///
+    /// # Panics
+    ///
+    /// In the same cases as [`msg_send!`][crate::msg_send].
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`msg_send!`][crate::msg_send], except that the types are already
+    /// given by `A` and `R`.
+    #[inline]
+    pub unsafe fn send<T>(self, receiver: &T, args: A) -> R
+    where
+        T: MessageReceiver + ?Sized,
+    {
+        match unsafe { receiver.send_message(self.sel, args) } {
+            Err(s) => panic!("{}", s),
+            Ok(r) => r,
+        }
+    }
+
+    /// Sends the message to the receiver's superclass with the given
+    /// arguments.
+    ///
+    /// # Panics
+    ///
+    /// In the same cases as [`msg_send!`][crate::msg_send].
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`msg_send!(super(...), ...)`][crate::msg_send], except that the types
+    /// are already given by `A` and `R`.
+    #[inline]
+    pub unsafe fn send_super<T>(self, receiver: &T, superclass: &Class, args: A) -> R
+    where
+        T: MessageReceiver + ?Sized,
+    {
+        match unsafe { receiver.send_super_message(superclass, self.sel, args) } {
+            Err(s) => panic!("{}", s),
+            Ok(r) => r,
+        }
+    }
+}
+
+/// The implementation of a method, looked up once with [`TypedSel::imp`],
+/// and then called directly.
+///
+/// Calling the implementation directly skips the method lookup (and the
+/// method cache) of `objc_msgSend`, which can be noticeable in tight loops.
+/// However, since the lookup is skipped, the implementation is a snapshot
+/// that won't reflect later changes to the class; it will be stale if the
+/// receiver's method is different from the one on the class it was looked up
+/// on. That happens when:
+///
+/// - The receiver is an instance of a subclass that overrides the method.
+///   Beware of class clusters like `NSArray`, where the receivers are
+///   instances of private subclasses; look up the method on the receiver's
+///   class instead.
+/// - The receiver's class is changed, e.g. by key-value observing.
+/// - The method's implementation is replaced, e.g. with
+///   `method_setImplementation`, `class_replaceMethod` or
+///   [`ClassExtension::replace_method`][crate::declare::ClassExtension::replace_method],
+///   or by a category loaded afterwards.
+///
+/// So the implementation should only be cached for as long as the classes
+/// involved are under your control, which is usually the duration of a loop
+/// over objects whose class was checked up front. Look it up again instead
+/// of keeping it around indefinitely.
+///
+/// # Example
+///
+/// ```no_run
+/// use objc2::runtime::Object;
+/// use objc2::{msg_send, typed_sel};
+///
+/// let array: &Object;
+/// # array = unsafe { &*(0 as *const Object) };
+/// let count: usize = unsafe { msg_send![array, count] };
+/// let object_at_index = typed_sel!(objectAtIndex: (usize) -> *mut Object)
+///     .imp(array.class())
+///     .unwrap();
+/// for i in 0..count {
+///     let obj = unsafe { object_at_index.call(&array, (i,)) };
+/// }
+/// ```
+pub struct CachedImp<A, R> {
+    imp: Imp,
+    cls: &'static Class,
+    sel: Sel,
+    p: PhantomData<fn(A) -> R>,
+}
+
+impl<A: MessageArguments, R: Encode> CachedImp<A, R> {
+    /// The implementation of the method.
+    #[inline]
+    pub fn imp(&self) -> Imp {
+        self.imp
+    }
+
+    /// The class that the method was looked up on.
+    #[inline]
+    pub fn class(&self) -> &'static Class {
+        self.cls
+    }
+
+    /// The selector of the method.
+    #[inline]
+    pub fn sel(&self) -> Sel {
+        self.sel
+    }
+
+    /// Calls the implementation with the given receiver and arguments.
+    ///
+    /// # Panics
+    ///
+    /// If the receiver is `nil`, since there's no message send to turn it
+    /// into a no-op. With debug assertions enabled, also if the receiver is
+    /// not an instance of the class that the method was looked up on (or of
+    /// a subclass).
+    ///
+    /// And if the `catch_all` feature is enabled and the method throws an
+    /// exception.
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`msg_send!`][crate::msg_send], except that the types are already
+    /// given by `A` and `R`.
+    ///
+    /// Additionally, the implementation must not be stale for the receiver,
+    /// see the type level documentation.
+    #[inline]
+    pub unsafe fn call<T>(&self, receiver: &T, args: A) -> R
+    where
+        T: MessageReceiver + ?Sized,
+    {
+        let obj = receiver.as_raw_receiver();
+        assert!(!obj.is_null(), "Cannot call {:?} with nil", self.sel);
+        debug_assert!(
+            unsafe { &*obj }.class().is_subclass_of(self.cls),
+            "{:?} was looked up on {:?}, but the receiver is a {:?}",
+            self.sel,
+            self.cls,
+            unsafe { &*obj }.class(),
+        );
+        let imp = self.imp;
+        let sel = self.sel;
+        match unsafe { conditional_try(|| A::__invoke(imp, obj, sel, args)) } {
+            Err(s) => panic!("{}", s),
+            Ok(r) => r,
+        }
+    }
+}
+
+impl<A, R> Clone for CachedImp<A, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, R> Copy for CachedImp<A, R> {}
+
+impl<A, R> fmt::Debug for CachedImp<A, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedImp")
+            .field("imp", &self.imp)
+            .field("cls", &self.cls)
+            .field("sel", &self.sel)
+            .finish()
+    }
+}
+
+// Manual impls, since the derives would require `A` and `R` to implement
+// the traits as well.
+
+impl<A, R> Clone for TypedSel<A, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, R> Copy for TypedSel<A, R> {}
+
+impl<A, R> PartialEq for TypedSel<A, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sel == other.sel
+    }
+}
+
+impl<A, R> Eq for TypedSel<A, R> {}
+
+impl<A: MessageArguments, R: Encode> fmt::Debug for TypedSel<A, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedSel")
+            .field("sel", &self.sel)
+            .field("args", &A::ENCODINGS)
+            .field("ret", &R::ENCODING)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_send() {
+        let obj = test_utils::custom_object();
+        let set_foo = typed_sel!(setFoo: (u32));
+        let foo = typed_sel!(foo -> u32);
+        assert_eq!(set_foo.sel(), sel!(setFoo:));
+        assert_eq!(foo.sel(), sel!(foo));
+
+        unsafe { set_foo.send(&obj, (4,)) };
+        assert_eq!(unsafe { foo.send(&obj, ()) }, 4);
+    }
+
+    #[test]
+    fn test_send_super() {
+        let obj = test_utils::custom_subclass_object();
+        let superclass = test_utils::custom_class();
+        let foo = typed_sel!(foo -> u32);
+        unsafe {
+            typed_sel!(setFoo: (u32)).send(&obj, (4,));
+            assert_eq!(foo.send_super(&obj, superclass, ()), 4);
+            // The subclass is overriden to return foo + 2
+            assert_eq!(foo.send(&obj, ()), 6);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "malloc")]
+    fn test_verify() {
+        let cls = test_utils::custom_class();
+        assert!(typed_sel!(foo -> u32).verify(cls).is_ok());
+        assert!(typed_sel!(setFoo: (u32)).verify(cls).is_ok());
+        assert!(typed_sel!(foo -> u64).verify(cls).is_err());
+        assert!(typed_sel!(foo -> *mut crate::runtime::Object)
+            .verify(cls)
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic = "accepts 1 arguments, but 2 were specified"]
+    fn test_argument_count_mismatch() {
+        let _ = TypedSel::<(u32, u32), ()>::new(sel!(setFoo:));
+    }
+
+    #[test]
+    fn test_imp() {
+        let obj = test_utils::custom_object();
+        let cls = test_utils::custom_class();
+        let set_foo = typed_sel!(setFoo: (u32)).imp(cls).unwrap();
+        let foo = typed_sel!(foo -> u32).imp(cls).unwrap();
+        assert_eq!(foo.class(), cls);
+        assert_eq!(foo.sel(), sel!(foo));
+
+        for i in 0..10 {
+            unsafe { set_foo.call(&obj, (i,)) };
+            assert_eq!(unsafe { foo.call(&obj, ()) }, i);
+        }
+
+        // Class methods are looked up on the metaclass
+        let class_foo = typed_sel!(classFoo -> u32).imp(cls.metaclass()).unwrap();
+        assert_eq!(unsafe { class_foo.call(&cls, ()) }, 7);
+
+        assert!(typed_sel!(bar -> u32).imp(cls).is_none());
+    }
+
+    #[test]
+    fn test_imp_subclass() {
+        let obj = test_utils::custom_subclass_object();
+        let foo = typed_sel!(foo -> u32);
+        unsafe { typed_sel!(setFoo: (u32)).send(&obj, (4,)) };
+
+        // The superclass' implementation is not the overridden one
+        let superclass_foo = foo.imp(test_utils::custom_class()).unwrap();
+        assert_eq!(unsafe { superclass_foo.call(&obj, ()) }, 4);
+        let subclass_foo = foo.imp(test_utils::custom_subclass()).unwrap();
+        assert_eq!(unsafe { subclass_foo.call(&obj, ()) }, 6);
+    }
+
+    #[test]
+    fn test_imp_dynamically_resolved() {
+        use crate::declare::ClassDecl;
+        use crate::runtime::Object;
+
+        extern "C" fn resolved_foo(_this: &Object, _cmd: Sel) -> u32 {
+            5
+        }
+
+        let mut decl =
+            ClassDecl::new("TypedSelResolverObject", test_utils::custom_class()).unwrap();
+        decl.set_instance_method_resolver(|ext, sel| {
+            if sel == sel!(resolvedFoo) {
+                let imp: extern "C" fn(&Object, Sel) -> u32 = resolved_foo;
+                unsafe { ext.add_method(sel, imp) };
+                true
+            } else {
+                false
+            }
+        });
+        let cls = decl.register();
+
+        let foo = typed_sel!(resolvedFoo -> u32).imp(cls).unwrap();
+        let obj: crate::rc::Id<Object, crate::rc::Owned> =
+            unsafe { msg_send_id![cls, new] }.unwrap();
+        assert_eq!(unsafe { foo.call(&obj, ()) }, 5);
+        assert!(typed_sel!(notResolved -> u32).imp(cls).is_none());
+    }
+
+    #[test]
+    #[should_panic = "Cannot call"]
+    fn test_imp_nil() {
+        let foo = typed_sel!(foo -> u32)
+            .imp(test_utils::custom_class())
+            .unwrap();
+        let nil: *const crate::runtime::Object = core::ptr::null();
+        let _ = unsafe { foo.call(&nil, ()) };
+    }
+}
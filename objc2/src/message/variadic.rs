@@ -0,0 +1,222 @@
+use core::mem;
+use core::ptr::NonNull;
+
+use super::MessageArguments;
+use crate::runtime::{Imp, Object, Sel};
+use crate::Encode;
+
+/// Types that may be passed in the variadic part of an Objective-C message.
+///
+/// C applies the "default argument promotions" to variadic arguments: `char`,
+/// `short` and `BOOL` are passed as `int`, and `float` as `double`. The
+/// callee reads them back as the promoted type, so passing an unpromoted
+/// type (e.g. an `u8` or an `f32`) would be a mismatch. To prevent that, this
+/// is only implemented for types that promotion leaves unchanged; convert
+/// anything else first, e.g. with `c_int::from(x)` or `f64::from(x)`.
+///
+/// # Safety
+///
+/// The type must be passed the same way as the C type it represents when
+/// used as a variadic argument, which means it must not be subject to the
+/// default argument promotions.
+pub unsafe trait VariadicArgument {}
+
+unsafe impl VariadicArgument for i32 {}
+unsafe impl VariadicArgument for u32 {}
+unsafe impl VariadicArgument for i64 {}
+unsafe impl VariadicArgument for u64 {}
+unsafe impl VariadicArgument for isize {}
+unsafe impl VariadicArgument for usize {}
+unsafe impl VariadicArgument for f64 {}
+
+unsafe impl VariadicArgument for Sel {}
+
+unsafe impl<T> VariadicArgument for *const T {}
+unsafe impl<T> VariadicArgument for *mut T {}
+unsafe impl<T> VariadicArgument for &T {}
+unsafe impl<T> VariadicArgument for &mut T {}
+unsafe impl<T> VariadicArgument for NonNull<T> {}
+unsafe impl<T> VariadicArgument for Option<&T> {}
+unsafe impl<T> VariadicArgument for Option<&mut T> {}
+unsafe impl<T> VariadicArgument for Option<NonNull<T>> {}
+
+/// Types that may be used as the variadic arguments of an Objective-C
+/// message.
+///
+/// This is implemented for tuples of up to 12 arguments, where each argument
+/// implements [`VariadicArgument`].
+///
+/// You should not need to implement this yourself.
+pub trait VariadicArguments {
+    /// Invoke an [`Imp`] taking one fixed argument and a variadic argument
+    /// list.
+    #[doc(hidden)]
+    unsafe fn __invoke1<A, R>(imp: Imp, obj: *mut Object, sel: Sel, a: A, vargs: Self) -> R;
+    #[doc(hidden)]
+    unsafe fn __invoke2<A, B, R>(
+        imp: Imp,
+        obj: *mut Object,
+        sel: Sel,
+        a: A,
+        b: B,
+        vargs: Self,
+    ) -> R;
+    #[doc(hidden)]
+    unsafe fn __invoke3<A, B, C, R>(
+        imp: Imp,
+        obj: *mut Object,
+        sel: Sel,
+        a: A,
+        b: B,
+        c: C,
+        vargs: Self,
+    ) -> R;
+    #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn __invoke4<A, B, C, D, R>(
+        imp: Imp,
+        obj: *mut Object,
+        sel: Sel,
+        a: A,
+        b: B,
+        c: C,
+        d: D,
+        vargs: Self,
+    ) -> R;
+}
+
+macro_rules! variadic_invoke {
+    ($fn:ident, ($($f:ident : $ft:ident),+), ($($v:ident),*)) => {
+        #[inline]
+        #[doc(hidden)]
+        unsafe fn $fn<$($ft,)+ R>(
+            imp: Imp,
+            obj: *mut Object,
+            sel: Sel,
+            $($f: $ft,)+
+            ($($v,)*): Self,
+        ) -> R {
+            // Like in `MessageArguments::__invoke`, the imp must be cast to
+            // the type of the method, which is variadic after the fixed
+            // arguments.
+            let imp: unsafe extern "C" fn(*mut Object, Sel, $($ft,)+ ...) -> R = unsafe {
+                mem::transmute(imp)
+            };
+            unsafe { imp(obj, sel, $($f,)+ $($v),*) }
+        }
+    };
+}
+
+macro_rules! variadic_args_impl {
+    ($($v:ident : $t:ident),*) => (
+        impl<$($t: VariadicArgument),*> VariadicArguments for ($($t,)*) {
+            variadic_invoke!(__invoke1, (a: A), ($($v),*));
+            variadic_invoke!(__invoke2, (a: A, b: B), ($($v),*));
+            variadic_invoke!(__invoke3, (a: A, b: B, c: C), ($($v),*));
+            variadic_invoke!(__invoke4, (a: A, b: B, c: C, d: D), ($($v),*));
+        }
+    );
+}
+
+variadic_args_impl!();
+variadic_args_impl!(v1: V1);
+variadic_args_impl!(v1: V1, v2: V2);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3, v4: V4);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3, v4: V4, v5: V5);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3, v4: V4, v5: V5, v6: V6);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3, v4: V4, v5: V5, v6: V6, v7: V7);
+variadic_args_impl!(v1: V1, v2: V2, v3: V3, v4: V4, v5: V5, v6: V6, v7: V7, v8: V8);
+variadic_args_impl!(
+    v1: V1,
+    v2: V2,
+    v3: V3,
+    v4: V4,
+    v5: V5,
+    v6: V6,
+    v7: V7,
+    v8: V8,
+    v9: V9
+);
+variadic_args_impl!(
+    v1: V1,
+    v2: V2,
+    v3: V3,
+    v4: V4,
+    v5: V5,
+    v6: V6,
+    v7: V7,
+    v8: V8,
+    v9: V9,
+    v10: V10
+);
+variadic_args_impl!(
+    v1: V1,
+    v2: V2,
+    v3: V3,
+    v4: V4,
+    v5: V5,
+    v6: V6,
+    v7: V7,
+    v8: V8,
+    v9: V9,
+    v10: V10,
+    v11: V11
+);
+variadic_args_impl!(
+    v1: V1,
+    v2: V2,
+    v3: V3,
+    v4: V4,
+    v5: V5,
+    v6: V6,
+    v7: V7,
+    v8: V8,
+    v9: V9,
+    v10: V10,
+    v11: V11,
+    v12: V12
+);
+
+/// The fixed arguments of a variadic Objective-C message.
+///
+/// This is implemented for tuples of 1 to 4 arguments, where each argument
+/// implements [`Encode`]; C requires at least one fixed argument before the
+/// variadic ones.
+///
+/// You should not need to implement this yourself.
+pub trait VariadicMessageArguments: MessageArguments {
+    /// Invoke an [`Imp`] with the given object, selector, fixed arguments
+    /// and variadic arguments.
+    #[doc(hidden)]
+    unsafe fn __invoke_variadic<V: VariadicArguments, R: Encode>(
+        imp: Imp,
+        obj: *mut Object,
+        sel: Sel,
+        args: Self,
+        vargs: V,
+    ) -> R;
+}
+
+macro_rules! variadic_message_args_impl {
+    ($fn:ident, $($a:ident : $t:ident),+) => (
+        impl<$($t: Encode),+> VariadicMessageArguments for ($($t,)+) {
+            #[inline]
+            #[doc(hidden)]
+            unsafe fn __invoke_variadic<V: VariadicArguments, R: Encode>(
+                imp: Imp,
+                obj: *mut Object,
+                sel: Sel,
+                ($($a,)+): Self,
+                vargs: V,
+            ) -> R {
+                unsafe { V::$fn(imp, obj, sel, $($a,)+ vargs) }
+            }
+        }
+    );
+}
+
+variadic_message_args_impl!(__invoke1, a: A);
+variadic_message_args_impl!(__invoke2, a: A, b: B);
+variadic_message_args_impl!(__invoke3, a: A, b: B, c: C);
+variadic_message_args_impl!(__invoke4, a: A, b: B, c: C, d: D);
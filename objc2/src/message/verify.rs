@@ -1,77 +1,295 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
+use std::error::Error;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::runtime::{Class, Method, Object, Sel};
+use crate::runtime::{Class, Object, Sel};
 use crate::{Encode, EncodeArguments, Encoding};
 
-pub enum VerificationError<'a> {
-    NilReceiver(Sel),
-    MethodNotFound(&'a Class, Sel),
-    MismatchedReturn(&'a Method, Encoding<'static>),
-    MismatchedArgumentsCount(&'a Method, usize),
-    MismatchedArgument(&'a Method, usize, Encoding<'static>),
+/// The ways the types of a message can fail to match the method that
+/// receives it.
+///
+/// Returned (as part of a [`SendError`][super::SendError]) when the
+/// `verify_message` feature is enabled, and by
+/// [`MessageReceiver::verify_message`][super::MessageReceiver::verify_message].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum VerificationError {
+    /// The message was sent to `nil`.
+    NilReceiver {
+        /// The selector of the message.
+        sel: Sel,
+    },
+    /// The class has no method for the selector, even after trying to
+    /// resolve it dynamically.
+    ///
+    /// For messages to classes, the class is the metaclass, and the method
+    /// that was searched for is a class method.
+    MethodNotFound {
+        /// The class that was searched.
+        cls: &'static Class,
+        /// The selector of the message.
+        sel: Sel,
+    },
+    /// The superclass given in a message to `super` is not a superclass of
+    /// the receiver's class.
+    ///
+    /// Note that when sending a class method to `super`, the superclass must
+    /// be the metaclass of the superclass.
+    InvalidSuperclass {
+        /// The selector of the message.
+        sel: Sel,
+        /// The class of the receiver.
+        cls: &'static Class,
+        /// The superclass that was given.
+        superclass: &'static Class,
+    },
+    /// The return type doesn't match the method's.
+    MismatchedReturn {
+        /// The selector of the message.
+        sel: Sel,
+        /// The type encoding of the method's return type.
+        expected: String,
+        /// The encoding of the return type that was given.
+        actual: Encoding<'static>,
+    },
+    /// A different number of arguments were given than the method takes
+    /// (including the receiver and the selector).
+    MismatchedArgumentsCount {
+        /// The selector of the message.
+        sel: Sel,
+        /// The number of arguments the method takes.
+        expected: usize,
+        /// The number of arguments that were given.
+        actual: usize,
+    },
+    /// The type of an argument doesn't match the method's.
+    MismatchedArgument {
+        /// The selector of the message.
+        sel: Sel,
+        /// The index of the argument (including the receiver and the
+        /// selector, so the first real argument has index 2).
+        index: usize,
+        /// The type encoding of the method's argument.
+        expected: String,
+        /// The encoding of the argument that was given.
+        actual: Encoding<'static>,
+    },
 }
 
-impl<'a> fmt::Display for VerificationError<'a> {
+impl fmt::Display for VerificationError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            VerificationError::NilReceiver(sel) => {
+        match self {
+            Self::NilReceiver { sel } => {
                 write!(f, "Messsaging {:?} to nil", sel)
             }
-            VerificationError::MethodNotFound(cls, sel) => {
+            Self::MethodNotFound { cls, sel } if cls.is_metaclass() => {
+                write!(f, "Class method {:?} not found on class {:?}", sel, cls)
+            }
+            Self::MethodNotFound { cls, sel } => {
                 write!(f, "Method {:?} not found on class {:?}", sel, cls)
             }
-            VerificationError::MismatchedReturn(method, ret) => {
-                let expected_ret = method.return_type();
+            Self::InvalidSuperclass {
+                sel,
+                cls,
+                superclass,
+            } => {
+                let kind = |cls: &Class| {
+                    if cls.is_metaclass() {
+                        "metaclass"
+                    } else {
+                        "class"
+                    }
+                };
+                write!(
+                    f,
+                    "Cannot send {:?} to super of {} {:?}, {} {:?} is not a superclass of it",
+                    sel,
+                    kind(cls),
+                    cls,
+                    kind(superclass),
+                    superclass
+                )
+            }
+            Self::MismatchedReturn {
+                sel,
+                expected,
+                actual,
+            } => {
                 write!(
                     f,
                     "Return type code {} does not match expected {} for method {:?}",
-                    ret,
-                    expected_ret,
-                    method.name()
+                    actual, expected, sel
                 )
             }
-            VerificationError::MismatchedArgumentsCount(method, count) => {
-                let expected_count = method.arguments_count();
+            Self::MismatchedArgumentsCount {
+                sel,
+                expected,
+                actual,
+            } => {
                 write!(
                     f,
                     "Method {:?} accepts {} arguments, but {} were given",
-                    method.name(),
-                    expected_count,
-                    count
+                    sel, expected, actual
                 )
             }
-            VerificationError::MismatchedArgument(method, i, arg) => {
-                let expected = method.argument_type(i).unwrap();
+            Self::MismatchedArgument {
+                sel,
+                index,
+                expected,
+                actual,
+            } => {
                 write!(
                     f,
                     "Method {:?} expected argument at index {} with type code {} but was given {}",
-                    method.name(),
-                    i,
-                    expected,
-                    arg
+                    sel, index, expected, actual
                 )
             }
         }
     }
 }
 
-pub(crate) fn verify_message_signature<A, R>(
+impl Error for VerificationError {}
+
+/// The argument and return type encodings of a successfully verified
+/// message.
+type Signature = (&'static [Encoding<'static>], Encoding<'static>);
+
+/// The signatures that have been verified, keyed by the address of the class
+/// and the selector.
+type Verified = BTreeMap<(usize, usize), Vec<Signature>>;
+
+const SHARDS: usize = 16;
+
+// Sharded by class to reduce contention between threads; the lookup on a cache
+// hit only takes a read lock.
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SHARD: RwLock<Verified> = RwLock::new(BTreeMap::new());
+static VERIFIED: [RwLock<Verified>; SHARDS] = [EMPTY_SHARD; SHARDS];
+
+fn shard(cls: usize) -> &'static RwLock<Verified> {
+    // Classes are aligned, so the low bits carry no information
+    &VERIFIED[(cls >> 4 ^ cls >> 10) % SHARDS]
+}
+
+// A panic can't leave the map in an inconsistent state, so we ignore
+// poisoning.
+
+fn read(cls: usize) -> RwLockReadGuard<'static, Verified> {
+    shard(cls).read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(cls: usize) -> RwLockWriteGuard<'static, Verified> {
+    shard(cls).write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the cached verifications of messages to the class (and its
+/// metaclass), since its address may be reused by another class.
+pub(crate) fn forget_verified(cls: &Class) {
+    for cls in [cls, cls.metaclass()] {
+        let cls = cls as *const Class as usize;
+        write(cls).retain(|&(c, _), _| c != cls);
+    }
+}
+
+/// Removes the cached verifications of messages with the selector to the
+/// class and its subclasses, since a method was added to or replaced on the
+/// class, which may change the types that the selector resolves to.
+///
+/// For class methods, `cls` is the metaclass.
+pub(crate) fn forget_verified_method(cls: &Class, sel: Sel) {
+    let sel = sel.as_ptr() as usize;
+    for shard in &VERIFIED {
+        let mut verified = shard.write().unwrap_or_else(|e| e.into_inner());
+        verified.retain(|&(c, s), _| {
+            // SAFETY: Disposed classes are removed from the cache, so the
+            // remaining ones are all valid.
+            s != sel || !unsafe { &*(c as *const Class) }.is_subclass_of(cls)
+        });
+    }
+}
+
+/// Verify the types of a message to the class.
+///
+/// Successful verifications are cached, so verifying the same message again
+/// is cheap. This assumes that the types of a method don't change once it
+/// has been added to a class, other than through [`ClassExtension`], which
+/// calls [`forget_verified_method`].
+///
+/// [`ClassExtension`]: crate::declare::ClassExtension
+pub(crate) fn verify_message_signature<A, R>(cls: &Class, sel: Sel) -> Result<(), VerificationError>
+where
+    A: EncodeArguments,
+    R: Encode,
+{
+    let key = (cls as *const Class as usize, sel.as_ptr() as usize);
+    let signature: Signature = (A::ENCODINGS, R::ENCODING);
+
+    if let Some(verified) = read(key.0).get(&key) {
+        if verified.contains(&signature) {
+            return Ok(());
+        }
+    }
+
+    verify_uncached::<A, R>(cls, sel)?;
+
+    let mut verified = write(key.0);
+    let verified = verified.entry(key).or_default();
+    if !verified.contains(&signature) {
+        verified.push(signature);
+    }
+    Ok(())
+}
+
+/// Verify the types of a message to `super`, which is resolved by looking up
+/// the method on `superclass` instead of on `cls`, the receiver's class.
+#[cfg_attr(not(feature = "verify_message"), allow(dead_code))]
+pub(crate) fn verify_super_message_signature<A, R>(
     cls: &Class,
+    superclass: &Class,
     sel: Sel,
-) -> Result<(), VerificationError<'_>>
+) -> Result<(), VerificationError>
 where
     A: EncodeArguments,
     R: Encode,
 {
-    let method = match cls.instance_method(sel) {
+    if !cls.is_subclass_of(superclass) {
+        // SAFETY: Classes are never deallocated once registered.
+        let cls: &'static Class = unsafe { &*(cls as *const Class) };
+        let superclass: &'static Class = unsafe { &*(superclass as *const Class) };
+        return Err(VerificationError::InvalidSuperclass {
+            sel,
+            cls,
+            superclass,
+        });
+    }
+    verify_message_signature::<A, R>(superclass, sel)
+}
+
+fn verify_uncached<A, R>(cls: &Class, sel: Sel) -> Result<(), VerificationError>
+where
+    A: EncodeArguments,
+    R: Encode,
+{
+    let method = match super::lookup_method(cls, sel) {
         Some(method) => method,
-        None => return Err(VerificationError::MethodNotFound(cls, sel)),
+        None => {
+            // SAFETY: Classes are never deallocated once registered.
+            let cls: &'static Class = unsafe { &*(cls as *const Class) };
+            return Err(VerificationError::MethodNotFound { cls, sel });
+        }
     };
 
     let ret = R::ENCODING;
     let expected_ret = method.return_type();
     if !ret.equivalent_to_str(&*expected_ret) {
-        return Err(VerificationError::MismatchedReturn(method, ret));
+        return Err(VerificationError::MismatchedReturn {
+            sel,
+            expected: expected_ret.to_string(),
+            actual: ret,
+        });
     }
 
     let self_and_cmd = [<*mut Object>::ENCODING, Sel::ENCODING];
@@ -80,15 +298,202 @@ where
     let count = self_and_cmd.len() + args.len();
     let expected_count = method.arguments_count();
     if count != expected_count {
-        return Err(VerificationError::MismatchedArgumentsCount(method, count));
+        return Err(VerificationError::MismatchedArgumentsCount {
+            sel,
+            expected: expected_count,
+            actual: count,
+        });
     }
 
     for (i, arg) in self_and_cmd.iter().chain(args).copied().enumerate() {
         let expected = method.argument_type(i).unwrap();
         if !arg.equivalent_to_str(&*expected) {
-            return Err(VerificationError::MismatchedArgument(method, i, arg));
+            return Err(VerificationError::MismatchedArgument {
+                sel,
+                index: i,
+                expected: expected.to_string(),
+                actual: arg,
+            });
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declare::ClassDecl;
+    use crate::test_utils;
+    use crate::MessageReceiver;
+
+    fn is_cached<A: EncodeArguments, R: Encode>(cls: &Class, sel: Sel) -> bool {
+        let key = (cls as *const Class as usize, sel.as_ptr() as usize);
+        let signature: Signature = (A::ENCODINGS, R::ENCODING);
+        matches!(read(key.0).get(&key), Some(verified) if verified.contains(&signature))
+    }
+
+    #[test]
+    fn test_cache() {
+        let cls = test_utils::custom_class();
+        let sel = sel!(setFoo:);
+        assert!(verify_message_signature::<(u32,), ()>(cls, sel).is_ok());
+        assert!(is_cached::<(u32,), ()>(cls, sel));
+        assert!(verify_message_signature::<(u32,), ()>(cls, sel).is_ok());
+
+        // Failures are not cached, and don't match cached successes
+        assert!(verify_message_signature::<(u64,), ()>(cls, sel).is_err());
+        assert!(!is_cached::<(u64,), ()>(cls, sel));
+        assert!(verify_message_signature::<(u32,), u32>(cls, sel).is_err());
+    }
+
+    #[test]
+    fn test_forget_verified() {
+        // Other tests don't use this class, so they won't verify it in the
+        // meantime
+        let cls = ClassDecl::new("VerifyCacheTestObject", class!(NSObject))
+            .unwrap()
+            .register();
+        let sel = sel!(hash);
+        assert!(verify_message_signature::<(), usize>(cls, sel).is_ok());
+        assert!(is_cached::<(), usize>(cls, sel));
+        forget_verified(cls);
+        assert!(!is_cached::<(), usize>(cls, sel));
+    }
+
+    #[test]
+    fn test_forget_verified_method() {
+        use crate::declare::ClassExtension;
+        use crate::runtime::Object;
+
+        extern "C" fn get_foo(_this: &Object, _cmd: Sel) -> u64 {
+            0
+        }
+
+        let superclass = ClassDecl::new("VerifyCacheExtensionObject", class!(NSObject))
+            .unwrap()
+            .register();
+        let cls = ClassDecl::new("VerifyCacheExtensionSubclass", superclass)
+            .unwrap()
+            .register();
+        let sel = sel!(hash);
+        assert!(verify_message_signature::<(), usize>(cls, sel).is_ok());
+        assert!(verify_message_signature::<(), usize>(superclass, sel).is_ok());
+
+        // Override the inherited method with different types on the
+        // superclass, which also affects the subclass
+        let mut ext = ClassExtension::new(superclass);
+        unsafe { ext.add_method(sel, get_foo as extern "C" fn(&Object, Sel) -> u64) };
+        assert!(!is_cached::<(), usize>(cls, sel));
+        assert!(!is_cached::<(), usize>(superclass, sel));
+        assert!(verify_message_signature::<(), usize>(cls, sel).is_err());
+        assert!(verify_message_signature::<(), u64>(cls, sel).is_ok());
+    }
+
+    #[test]
+    fn test_class_method() {
+        let cls = test_utils::custom_class();
+        let sel = sel!(classFoo);
+        // Class methods are instance methods of the metaclass
+        assert!(verify_message_signature::<(), u32>(cls.metaclass(), sel).is_ok());
+        assert!(cls.verify_message::<(), u32>(sel).is_ok());
+        assert!(verify_message_signature::<(), u32>(cls, sel).is_err());
+
+        let err = verify_message_signature::<(), u32>(cls.metaclass(), sel!(foo)).unwrap_err();
+        assert_eq!(
+            err,
+            VerificationError::MethodNotFound {
+                cls: cls.metaclass(),
+                sel: sel!(foo)
+            }
+        );
+        assert!(err.to_string().starts_with("Class method"));
+    }
+
+    #[test]
+    fn test_super() {
+        let subclass = test_utils::custom_subclass();
+        let superclass = test_utils::custom_class();
+        let res = verify_super_message_signature::<(), u32>(subclass, superclass, sel!(foo));
+        assert!(res.is_ok());
+        let res = verify_super_message_signature::<(), u32>(
+            subclass.metaclass(),
+            superclass.metaclass(),
+            sel!(classFoo),
+        );
+        assert!(res.is_ok());
+
+        // Class methods must be sent to the super metaclass
+        let err = verify_super_message_signature::<(), u32>(
+            subclass.metaclass(),
+            superclass,
+            sel!(classFoo),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            VerificationError::InvalidSuperclass {
+                sel: sel!(classFoo),
+                cls: subclass.metaclass(),
+                superclass,
+            }
+        );
+        // And the superclass must actually be a superclass
+        let res = verify_super_message_signature::<(), u32>(superclass, subclass, sel!(foo));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_category() {
+        extern "C" fn category_foo(_this: &Object, _cmd: Sel) -> u32 {
+            3
+        }
+
+        let cls = ClassDecl::new("VerifyCategoryObject", test_utils::custom_class())
+            .unwrap()
+            .register();
+        let sel = sel!(categoryFoo);
+        assert!(verify_message_signature::<(), u32>(cls, sel).is_err());
+        let mut ext = crate::declare::ClassExtension::new(cls);
+        unsafe { ext.add_method(sel, category_foo as extern "C" fn(&Object, Sel) -> u32) };
+        assert!(verify_message_signature::<(), u32>(cls, sel).is_ok());
+        assert!(verify_message_signature::<(), u64>(cls, sel).is_err());
+    }
+
+    #[test]
+    fn test_dynamically_resolved() {
+        extern "C" fn resolved_foo(_this: &Object, _cmd: Sel) -> u32 {
+            5
+        }
+        extern "C" fn resolved_class_foo(_this: &Class, _cmd: Sel) -> u32 {
+            6
+        }
+
+        let mut decl = ClassDecl::new("VerifyResolverObject", test_utils::custom_class()).unwrap();
+        decl.set_instance_method_resolver(|ext, sel| {
+            if sel == sel!(resolvedFoo) {
+                let imp: extern "C" fn(&Object, Sel) -> u32 = resolved_foo;
+                unsafe { ext.add_method(sel, imp) };
+                true
+            } else {
+                false
+            }
+        });
+        decl.set_class_method_resolver(|ext, sel| {
+            if sel == sel!(resolvedClassFoo) {
+                let imp: extern "C" fn(&Class, Sel) -> u32 = resolved_class_foo;
+                unsafe { ext.add_class_method(sel, imp) };
+                true
+            } else {
+                false
+            }
+        });
+        let cls = decl.register();
+
+        assert!(verify_message_signature::<(), u32>(cls, sel!(resolvedFoo)).is_ok());
+        let res = verify_message_signature::<(), u32>(cls.metaclass(), sel!(resolvedClassFoo));
+        assert!(res.is_ok());
+        let err = verify_message_signature::<(), u32>(cls, sel!(notResolved)).unwrap_err();
+        assert!(matches!(err, VerificationError::MethodNotFound { .. }));
+    }
+}
@@ -0,0 +1,197 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr::NonNull;
+
+use super::{Id, Ownership};
+use crate::__macro_helpers::in_selector_family;
+use crate::runtime::Sel;
+use crate::{ffi, Message, MessageArguments, MessageReceiver, SendError};
+
+/// An Objective-C object that has been allocated, but not yet initialized.
+///
+/// Objects are unsafe to use between being allocated and initialized, so
+/// this type doesn't allow sending any messages other than ones in the
+/// `init` family, which consume it and return an [`Id`] to the initialized
+/// object.
+///
+/// This is what [`msg_send_id!`] returns for selectors in the `alloc`
+/// family, and what it requires the receiver of selectors in the `init`
+/// family to be.
+///
+/// Dropping the [`Allocated`] without initializing it releases the object.
+///
+/// [`msg_send_id!`]: crate::msg_send_id
+///
+/// # Examples
+///
+/// ```no_run
+/// use objc2::rc::{Allocated, Id, Owned};
+/// use objc2::runtime::Object;
+/// use objc2::{class, msg_send_id};
+///
+/// let obj: Option<Allocated<Object>> = unsafe { msg_send_id![class!(NSObject), alloc] };
+/// let obj: Option<Id<Object, Owned>> = unsafe { msg_send_id![obj, init] };
+/// ```
+#[repr(transparent)]
+pub struct Allocated<T: ?Sized> {
+    /// The allocated object. Has a +1 retain count.
+    ptr: NonNull<T>,
+    item: PhantomData<T>,
+}
+
+impl<T: Message + ?Sized> Allocated<T> {
+    /// Constructs an [`Allocated`] from a pointer to an object that has just
+    /// been allocated, or returns [`None`] if the pointer is null.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be the result of sending a selector in the `alloc`
+    /// family (e.g. `alloc` or `allocWithZone:`), and must not be used
+    /// afterwards.
+    #[inline]
+    pub unsafe fn new(ptr: *mut T) -> Option<Self> {
+        NonNull::new(ptr).map(|ptr| Self {
+            ptr,
+            item: PhantomData,
+        })
+    }
+
+    /// Returns a raw pointer to the allocated object.
+    ///
+    /// The object must not be messaged through the pointer.
+    #[inline]
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.ptr.as_ptr()
+    }
+
+    /// Consumes the [`Allocated`] without releasing it, returning a pointer
+    /// that carries its +1 retain count.
+    #[inline]
+    pub(crate) fn consume_as_ptr(this: Self) -> *mut T {
+        ManuallyDrop::new(this).ptr.as_ptr()
+    }
+
+    /// Initializes the object by sending it the given selector in the `init`
+    /// family with the given arguments.
+    ///
+    /// Returns [`None`] if the initializer failed and returned `nil`; in that
+    /// case the initializer has already released the object, as is the
+    /// convention in Objective-C.
+    ///
+    /// If the selector is known at compile-time, it is recommended to use
+    /// the [`msg_send_id!`][crate::msg_send_id] macro rather than this
+    /// method.
+    ///
+    /// # Panics
+    ///
+    /// If the selector is not in the `init` family.
+    ///
+    /// # Safety
+    ///
+    /// This shares the same safety requirements as
+    /// [`MessageReceiver::send_message`], and the initializer must return an
+    /// object of type `T` with the given ownership.
+    pub unsafe fn send_init<A, O>(self, sel: Sel, args: A) -> Result<Option<Id<T, O>>, SendError>
+    where
+        A: MessageArguments,
+        O: Ownership,
+    {
+        assert!(
+            in_selector_family(sel.name().as_bytes(), b"init"),
+            "Allocated objects can only be sent selectors in the init family, got {:?}",
+            sel,
+        );
+        unsafe { self.send_init_unchecked(sel, args) }
+    }
+
+    /// Like [`Allocated::send_init`], but without checking the selector.
+    #[inline]
+    pub(crate) unsafe fn send_init_unchecked<A, O>(
+        self,
+        sel: Sel,
+        args: A,
+    ) -> Result<Option<Id<T, O>>, SendError>
+    where
+        A: MessageArguments,
+        O: Ownership,
+    {
+        // The initializer consumes the object
+        let this = Self::consume_as_ptr(self);
+        match unsafe { this.send_message(sel, args) } {
+            // SAFETY: Initializers return a retained object, and the caller
+            // upholds the rest.
+            Ok(ptr) => Ok(NonNull::new(ptr).map(|ptr| unsafe { Id::new(ptr) })),
+            Err(err) => {
+                // If verification failed, the message was never sent, so the
+                // object is still ours to release.
+                #[cfg(feature = "malloc")]
+                if matches!(err, SendError::Verification(_)) {
+                    // SAFETY: The object has a +1 retain count that we own.
+                    unsafe { ffi::objc_release(this as *mut ffi::objc_object) };
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for Allocated<T> {
+    #[inline]
+    fn drop(&mut self) {
+        let ptr = self.ptr.as_ptr() as *mut ffi::objc_object;
+        // SAFETY: The object has a +1 retain count that we own.
+        unsafe { ffi::objc_release(ptr) };
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Allocated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Allocated").field(&self.ptr).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::Owned;
+    use crate::runtime::Object;
+
+    #[test]
+    fn test_send_init() {
+        let obj: Allocated<Object> = unsafe { msg_send_id![class!(NSObject), alloc] }.unwrap();
+        let obj: Id<Object, Owned> = unsafe { obj.send_init(sel!(init), ()) }.unwrap().unwrap();
+        let retain_count: usize = unsafe { msg_send![obj, retainCount] };
+        assert_eq!(retain_count, 1);
+    }
+
+    #[test]
+    fn test_drop_uninitialized() {
+        let obj: Option<Allocated<Object>> = unsafe { msg_send_id![class!(NSObject), alloc] };
+        drop(obj);
+    }
+
+    #[test]
+    #[cfg(feature = "verify_message")]
+    fn test_send_init_verification_error() {
+        let obj: Allocated<Object> = unsafe { msg_send_id![class!(NSObject), alloc] }.unwrap();
+        let ptr = Allocated::as_ptr(&obj) as *mut Object;
+        // Keep the object alive to check that it was released
+        unsafe { ffi::objc_retain(ptr as *mut ffi::objc_object) };
+
+        let res: Result<Option<Id<Object, Owned>>, _> =
+            unsafe { obj.send_init(sel!(initWithFoo:), (5u32,)) };
+        assert!(matches!(res, Err(SendError::Verification(_))));
+
+        let retain_count: usize = unsafe { msg_send![ptr, retainCount] };
+        assert_eq!(retain_count, 1);
+        unsafe { ffi::objc_release(ptr as *mut ffi::objc_object) };
+    }
+
+    #[test]
+    #[should_panic = "can only be sent selectors in the init family"]
+    fn test_send_non_init() {
+        let obj: Allocated<Object> = unsafe { msg_send_id![class!(NSObject), alloc] }.unwrap();
+        let _: Option<Id<Object, Owned>> = unsafe { obj.send_init(sel!(description), ()) }.unwrap();
+    }
+}
@@ -26,6 +26,7 @@
 //! [mem-cf]: https://developer.apple.com/library/archive/documentation/CoreFoundation/Conceptual/CFMemoryMgmt/CFMemoryMgmt.html
 //! [mem-debug]: https://developer.apple.com/library/archive/documentation/Performance/Conceptual/ManagingMemory/Articles/MallocDebug.html
 
+mod allocated;
 mod autorelease;
 mod id;
 mod id_forwarding_impls;
@@ -33,6 +34,7 @@ mod id_traits;
 mod ownership;
 mod weak_id;
 
+pub use self::allocated::Allocated;
 pub use self::autorelease::{autoreleasepool, AutoreleasePool, AutoreleaseSafe};
 pub use self::id::Id;
 pub use self::id_traits::{DefaultId, SliceId, SliceIdMut};
@@ -43,7 +45,7 @@ pub use self::weak_id::WeakId;
 mod tests {
     use core::mem::size_of;
 
-    use super::{Id, Owned, Shared, WeakId};
+    use super::{Allocated, Id, Owned, Shared, WeakId};
 
     struct TestType {
         _data: [u8; 0], // TODO: `UnsafeCell`?
@@ -62,6 +64,11 @@ mod tests {
             size_of::<&TestType>()
         );
 
+        assert_eq!(
+            size_of::<Option<Allocated<TestType>>>(),
+            size_of::<&TestType>()
+        );
+
         assert_eq!(
             size_of::<Option<WeakId<TestType>>>(),
             size_of::<*const ()>()
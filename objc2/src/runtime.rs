@@ -3,14 +3,18 @@
 //! For more information on foreign functions, see Apple's documentation:
 //! <https://developer.apple.com/library/mac/documentation/Cocoa/Reference/ObjCRuntimeRef/index.html>
 
+use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::fmt;
+use core::iter;
 use core::panic::{RefUnwindSafe, UnwindSafe};
 use core::ptr;
 use core::str;
 #[cfg(feature = "malloc")]
 use malloc_buf::Malloc;
 use std::ffi::{CStr, CString};
+#[cfg(all(apple, feature = "malloc"))]
+use std::os::raw::c_char;
 #[cfg(feature = "malloc")]
 use std::os::raw::c_uint;
 
@@ -44,6 +48,17 @@ pub struct Ivar(ffi::objc_ivar);
 #[repr(C)]
 pub struct Method(ffi::objc_method);
 
+/// A type that represents a declared property of a class or protocol.
+#[repr(C)]
+pub struct Property(ffi::objc_property);
+
+/// A description of a method declared by a protocol.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MethodDescription {
+    sel: Sel,
+    types: &'static str,
+}
+
 /// A type that represents an Objective-C class.
 #[repr(C)]
 pub struct Class(ffi::objc_class);
@@ -201,8 +216,15 @@ impl Method {
         }
     }
 
-    // method_getTypeEncoding, efficient version of:
-    // -> return_type() + sum(argument_type(i) for i in arguments_count())
+    /// Returns the full type encoding of self, that is, the encoding of the
+    /// return type followed by the encodings of the arguments.
+    ///
+    /// Note that this may contain stack offsets (e.g. `"I16@0:8"`), depending
+    /// on how the method was declared.
+    pub fn type_encoding(&self) -> &str {
+        let encoding = unsafe { CStr::from_ptr(ffi::method_getTypeEncoding(self.as_ptr())) };
+        str::from_utf8(encoding.to_bytes()).unwrap()
+    }
 
     /// Returns the number of arguments accepted by self.
     pub fn arguments_count(&self) -> usize {
@@ -224,6 +246,99 @@ unsafe impl Send for Method {}
 impl UnwindSafe for Method {}
 impl RefUnwindSafe for Method {}
 
+impl MethodDescription {
+    /// Converts the raw description, returning [`None`] if it is empty (or
+    /// if the types aren't valid UTF-8, which the runtime doesn't check).
+    ///
+    /// # Safety
+    ///
+    /// The description must come from a protocol, whose method types are
+    /// never deallocated.
+    pub(crate) unsafe fn from_raw(desc: ffi::objc_method_description) -> Option<Self> {
+        if desc.name.is_null() || desc.types.is_null() {
+            None
+        } else {
+            let types = unsafe { CStr::from_ptr(desc.types) };
+            Some(Self {
+                sel: unsafe { Sel::from_ptr(desc.name as *const c_void) },
+                types: str::from_utf8(types.to_bytes()).ok()?,
+            })
+        }
+    }
+
+    /// Returns the name of the described method.
+    pub fn name(&self) -> Sel {
+        self.sel
+    }
+
+    /// Returns the type encoding of the described method.
+    pub fn type_encoding(&self) -> &'static str {
+        self.types
+    }
+}
+
+impl fmt::Debug for MethodDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodDescription")
+            .field("name", &self.sel)
+            .field("types", &self.types)
+            .finish()
+    }
+}
+
+impl Property {
+    pub(crate) fn as_ptr(&self) -> *const ffi::objc_property {
+        self as *const Self as *const _
+    }
+
+    /// Returns the name of self.
+    pub fn name(&self) -> &str {
+        let name = unsafe { CStr::from_ptr(ffi::property_getName(self.as_ptr())) };
+        str::from_utf8(name.to_bytes()).unwrap()
+    }
+
+    /// Returns the attribute string of self.
+    ///
+    /// See Apple's [documentation on declared properties][props] for the
+    /// format of this string.
+    ///
+    /// [props]: https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/ObjCRuntimeGuide/Articles/ocrtPropertyIntrospection.html
+    pub fn attributes(&self) -> &str {
+        let attributes = unsafe { CStr::from_ptr(ffi::property_getAttributes(self.as_ptr())) };
+        str::from_utf8(attributes.to_bytes()).unwrap()
+    }
+
+    /// Returns the value of the attribute with the given name, or [`None`]
+    /// if self does not have that attribute.
+    ///
+    /// Note that attributes that are just flags (like `"N"` for
+    /// `nonatomic`) have an empty string as their value.
+    #[cfg(feature = "malloc")]
+    pub fn attribute_value(&self, name: &str) -> Option<Malloc<str>> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let value = ffi::property_copyAttributeValue(self.as_ptr(), name.as_ptr());
+            if value.is_null() {
+                None
+            } else {
+                Some(Malloc::from_c_str(value).unwrap())
+            }
+        }
+    }
+}
+
+// SAFETY: Property is immutable (and can be retrieved from Class anyhow).
+unsafe impl Sync for Property {}
+unsafe impl Send for Property {}
+impl UnwindSafe for Property {}
+impl RefUnwindSafe for Property {}
+
+impl fmt::Debug for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl Class {
     pub(crate) fn as_ptr(&self) -> *const ffi::objc_class {
         self as *const Self as *const _
@@ -261,6 +376,65 @@ impl Class {
         unsafe { ffi::objc_getClassList(ptr::null_mut(), 0) as usize }
     }
 
+    /// Obtains the list of registered classes, without requiring the
+    /// `malloc` feature.
+    fn class_list() -> Vec<&'static Self> {
+        let mut classes: Vec<*const ffi::objc_class> = Vec::new();
+        // The number of classes may change between the calls, so loop until
+        // the buffer is large enough.
+        loop {
+            let count = Self::classes_count();
+            classes.resize(count, ptr::null());
+            let count = unsafe { ffi::objc_getClassList(classes.as_mut_ptr(), count as _) };
+            if count as usize <= classes.len() {
+                classes.truncate(count as usize);
+                break;
+            }
+        }
+        classes
+            .into_iter()
+            .map(|cls| unsafe { &*(cls as *const Self) })
+            .collect()
+    }
+
+    /// Returns the names of all the loaded images (executables and dynamic
+    /// libraries) that contain Objective-C classes.
+    ///
+    /// This is only available on Apple platforms.
+    #[cfg(all(apple, feature = "malloc"))]
+    pub fn image_names() -> Vec<&'static str> {
+        let names = unsafe {
+            let mut count: c_uint = 0;
+            let names = ffi::objc_copyImageNames(&mut count);
+            Malloc::from_array(names as *mut *const c_char, count as usize)
+        };
+        names
+            .iter()
+            .map(|&name| unsafe { CStr::from_ptr(name) }.to_str().unwrap())
+            .collect()
+    }
+
+    /// Returns the classes defined in the loaded image with the given name
+    /// (as returned by [`Class::image_names`] or [`Class::image_name`]).
+    ///
+    /// This is only available on Apple platforms.
+    #[cfg(all(apple, feature = "malloc"))]
+    pub fn classes_for_image(image: &str) -> Vec<&'static Self> {
+        let image = CString::new(image).unwrap();
+        let names = unsafe {
+            let mut count: c_uint = 0;
+            let names = ffi::objc_copyClassNamesForImage(image.as_ptr(), &mut count);
+            Malloc::from_array(names as *mut *const c_char, count as usize)
+        };
+        names
+            .iter()
+            .filter_map(|&name| {
+                let cls = unsafe { ffi::objc_getClass(name) };
+                unsafe { cls.cast::<Self>().as_ref() }
+            })
+            .collect()
+    }
+
     /// Returns the name of the class.
     pub fn name(&self) -> &str {
         let name = unsafe { CStr::from_ptr(ffi::class_getName(self.as_ptr())) };
@@ -279,6 +453,50 @@ impl Class {
         }
     }
 
+    /// Returns an iterator over the superclasses of self, starting with the
+    /// direct superclass and ending with the root class.
+    pub fn superclasses(&self) -> impl Iterator<Item = &Class> {
+        iter::successors(self.superclass(), |cls| cls.superclass())
+    }
+
+    /// Checks whether self is the same class as, or a subclass of, the given
+    /// class.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        self == other || self.superclasses().any(|cls| cls == other)
+    }
+
+    /// Returns the registered classes whose direct superclass is self.
+    pub fn subclasses(&self) -> Vec<&'static Class> {
+        Self::class_list()
+            .into_iter()
+            .filter(|cls| cls.superclass() == Some(self))
+            .collect()
+    }
+
+    /// Returns all registered classes that inherit from self, both directly
+    /// and indirectly.
+    pub fn all_subclasses(&self) -> Vec<&'static Class> {
+        Self::class_list()
+            .into_iter()
+            .filter(|cls| cls.superclasses().any(|superclass| superclass == self))
+            .collect()
+    }
+
+    /// Returns the name of the loaded image (executable or dynamic library)
+    /// that the class was defined in, or [`None`] if it was declared at
+    /// runtime.
+    ///
+    /// This is only available on Apple platforms.
+    #[cfg(apple)]
+    pub fn image_name(&self) -> Option<&'static str> {
+        let name = unsafe { ffi::class_getImageName(self.as_ptr()) };
+        if name.is_null() {
+            None
+        } else {
+            Some(unsafe { CStr::from_ptr(name) }.to_str().unwrap())
+        }
+    }
+
     /// Returns the metaclass of self.
     pub fn metaclass(&self) -> &Self {
         unsafe { &*(ffi::object_getClass(self.as_ptr() as *const _) as *const Self) }
@@ -286,8 +504,8 @@ impl Class {
 
     // objc_getMetaClass -> Same as `Class::get(name).metaclass()`
 
-    #[allow(unused)]
-    fn is_metaclass(&self) -> bool {
+    #[cfg_attr(not(feature = "malloc"), allow(unused))]
+    pub(crate) fn is_metaclass(&self) -> bool {
         unsafe { Bool::from_raw(ffi::class_isMetaClass(self.as_ptr())).is_true() }
     }
 
@@ -310,7 +528,21 @@ impl Class {
         }
     }
 
-    // fn class_method(&self, sel: Sel) -> Option<&Method>;
+    /// Returns a specified class method for self, or [`None`] if self and
+    /// its superclasses do not contain a class method with the specified
+    /// selector.
+    ///
+    /// Same as `cls.metaclass().instance_method(sel)`.
+    pub fn class_method(&self, sel: Sel) -> Option<&Method> {
+        unsafe {
+            let method = ffi::class_getClassMethod(self.as_ptr(), sel.ptr);
+            if method.is_null() {
+                None
+            } else {
+                Some(&*(method as *const Method))
+            }
+        }
+    }
 
     /// Returns the ivar for a specified instance variable of self, or
     /// [`None`] if self has no ivar with the given name.
@@ -381,10 +613,27 @@ impl Class {
         }
     }
 
-    // fn property(&self, name: &str) -> Option<&Property>;
-    // fn properties(&self) -> Malloc<[&Property]>;
+    /// Returns the property with the given name declared by self or its
+    /// superclasses, or [`None`] if no such property exists.
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let property = ffi::class_getProperty(self.as_ptr(), name.as_ptr());
+            property.cast::<Property>().as_ref()
+        }
+    }
+
+    /// Describes the properties declared by self.
+    #[cfg(feature = "malloc")]
+    pub fn properties(&self) -> Malloc<[&Property]> {
+        unsafe {
+            let mut count: c_uint = 0;
+            let properties = ffi::class_copyPropertyList(self.as_ptr(), &mut count);
+            Malloc::from_array(properties as *mut _, count as usize)
+        }
+    }
+
     // unsafe fn replace_method(&self, name: Sel, imp: Imp, types: &str) -> Imp;
-    // unsafe fn replace_property(&self, name: &str, attributes: &[ffi::objc_property_attribute_t]);
     // unsafe fn set_ivar_layout(&mut self, layout: &[u8]);
     // fn method_imp(&self, name: Sel) -> Imp; // + _stret
     // fn responds_to(&self, sel: Sel) -> bool;
@@ -458,6 +707,91 @@ impl Protocol {
         }
     }
 
+    /// Returns the description of the method with the given selector
+    /// declared by self or the protocols it conforms to, or [`None`] if no
+    /// such method exists.
+    ///
+    /// Only the methods matching `is_required` and `is_instance_method` are
+    /// searched.
+    pub fn method_description(
+        &self,
+        sel: Sel,
+        is_required: bool,
+        is_instance_method: bool,
+    ) -> Option<MethodDescription> {
+        unsafe {
+            let desc = ffi::protocol_getMethodDescription(
+                self.as_ptr(),
+                sel.ptr,
+                Bool::new(is_required).as_raw(),
+                Bool::new(is_instance_method).as_raw(),
+            );
+            MethodDescription::from_raw(desc)
+        }
+    }
+
+    /// Describes the methods declared by self (but not the protocols it
+    /// conforms to) that match `is_required` and `is_instance_method`.
+    #[cfg(feature = "malloc")]
+    pub fn method_descriptions(
+        &self,
+        is_required: bool,
+        is_instance_method: bool,
+    ) -> Vec<MethodDescription> {
+        let descriptions = unsafe {
+            let mut count: c_uint = 0;
+            let descriptions = ffi::protocol_copyMethodDescriptionList(
+                self.as_ptr(),
+                Bool::new(is_required).as_raw(),
+                Bool::new(is_instance_method).as_raw(),
+                &mut count,
+            );
+            Malloc::from_array(descriptions, count as usize)
+        };
+        descriptions
+            .iter()
+            .filter_map(|&desc| unsafe { MethodDescription::from_raw(desc) })
+            .collect()
+    }
+
+    /// Returns the property with the given name declared by self or the
+    /// protocols it conforms to, or [`None`] if no such property exists.
+    ///
+    /// Only the properties matching `is_required` and `is_instance_property`
+    /// are searched.
+    pub fn property(
+        &self,
+        name: &str,
+        is_required: bool,
+        is_instance_property: bool,
+    ) -> Option<&Property> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let property = ffi::protocol_getProperty(
+                self.as_ptr(),
+                name.as_ptr(),
+                Bool::new(is_required).as_raw(),
+                Bool::new(is_instance_property).as_raw(),
+            );
+            property.cast::<Property>().as_ref()
+        }
+    }
+
+    /// Describes the required instance properties declared by self (but not
+    /// the protocols it conforms to).
+    ///
+    /// The other kinds of properties can't be listed without
+    /// `protocol_copyPropertyList2`, which requires macOS 10.12 / iOS 10.0;
+    /// use [`Protocol::property`] to look them up by name instead.
+    #[cfg(feature = "malloc")]
+    pub fn properties(&self) -> Malloc<[&Property]> {
+        unsafe {
+            let mut count: c_uint = 0;
+            let properties = ffi::protocol_copyPropertyList(self.as_ptr(), &mut count);
+            Malloc::from_array(properties as *mut _, count as usize)
+        }
+    }
+
     /// Checks whether this protocol conforms to the specified protocol.
     pub fn conforms_to(&self, proto: &Protocol) -> bool {
         unsafe {
@@ -522,6 +856,38 @@ impl Object {
         unsafe { &*(ffi::object_getClass(self.as_ptr()) as *const Class) }
     }
 
+    /// Changes the class of this object, returning the previous class.
+    ///
+    /// This is sometimes known as "isa-swizzling". See also
+    /// [`ClassDecl::isa_swizzle`][crate::declare::ClassDecl::isa_swizzle].
+    ///
+    /// # Panics
+    ///
+    /// If the instance size of the new class differs from that of the
+    /// object's current class.
+    ///
+    /// # Safety
+    ///
+    /// The new class must be compatible with the object, i.e. it must have
+    /// the same instance variables, and the object must be valid to use with
+    /// every method of the new class (usually it is a subclass or superclass
+    /// of the current class that doesn't add any instance variables).
+    ///
+    /// Additionally, no references to the object that rely on it being of
+    /// the previous class may be used afterwards.
+    pub unsafe fn set_class(&self, cls: &Class) -> &'static Class {
+        let current = self.class();
+        assert_eq!(
+            current.instance_size(),
+            cls.instance_size(),
+            "Cannot change class of object from {:?} to {:?}, the instance sizes differ",
+            current,
+            cls,
+        );
+        let ptr = self.as_ptr() as *mut ffi::objc_object;
+        unsafe { &*(ffi::object_setClass(ptr, cls.as_ptr()) as *const Class) }
+    }
+
     /// Returns a shared reference to the ivar with the given name.
     ///
     /// # Panics
@@ -609,8 +975,11 @@ impl fmt::Debug for Object {
 #[cfg(test)]
 mod tests {
     use alloc::string::ToString;
+    use alloc::vec::Vec;
 
-    use super::{Bool, Class, Imp, Ivar, Method, Object, Protocol, Sel};
+    use super::{
+        Bool, Class, Imp, Ivar, Method, MethodDescription, Object, Property, Protocol, Sel,
+    };
     use crate::test_utils;
     use crate::Encode;
 
@@ -643,6 +1012,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_class_method() {
+        let cls = test_utils::custom_class();
+        let method = cls.class_method(sel!(classFoo)).unwrap();
+        assert_eq!(method.name(), sel!(classFoo));
+        assert_eq!(
+            cls.metaclass()
+                .instance_method(sel!(classFoo))
+                .map(|m| m.name()),
+            Some(sel!(classFoo))
+        );
+        assert!(cls.instance_method(sel!(classFoo)).is_none());
+        assert!(cls.class_method(sel!(foo)).is_none());
+    }
+
     #[test]
     fn test_class() {
         let cls = test_utils::custom_class();
@@ -651,6 +1035,9 @@ mod tests {
         assert!(cls.superclass().is_none());
 
         assert_eq!(Class::get(cls.name()), Some(cls));
+        assert_eq!(class!(CustomObject), cls);
+        assert_eq!(get_class!(CustomObject), Some(cls));
+        assert!(get_class!(NonExistantClass).is_none());
 
         let metaclass = cls.metaclass();
         // The metaclass of a root class is a subclass of the root class
@@ -660,6 +1047,36 @@ mod tests {
         assert_eq!(subclass.superclass().unwrap(), cls);
     }
 
+    #[test]
+    fn test_class_hierarchy() {
+        let cls = test_utils::custom_class();
+        let subclass = test_utils::custom_subclass();
+
+        assert_eq!(cls.superclasses().count(), 0);
+        assert_eq!(subclass.superclasses().collect::<Vec<_>>(), [cls]);
+
+        assert!(cls.is_subclass_of(cls));
+        assert!(subclass.is_subclass_of(cls));
+        assert!(!cls.is_subclass_of(subclass));
+
+        assert!(cls.subclasses().contains(&subclass));
+        assert!(cls.all_subclasses().contains(&subclass));
+        assert!(subclass.subclasses().is_empty());
+        assert!(!cls.subclasses().contains(&cls));
+    }
+
+    #[test]
+    #[cfg(all(apple, feature = "malloc"))]
+    fn test_image_names() {
+        // Declared at runtime
+        assert_eq!(test_utils::custom_class().image_name(), None);
+
+        let cls = class!(NSObject);
+        let image = cls.image_name().unwrap();
+        assert!(Class::image_names().contains(&image));
+        assert!(Class::classes_for_image(image).contains(&cls));
+    }
+
     #[test]
     fn test_classes_count() {
         assert!(Class::classes_count() > 0);
@@ -676,12 +1093,72 @@ mod tests {
     fn test_protocol() {
         let proto = test_utils::custom_protocol();
         assert_eq!(proto.name(), "CustomProtocol");
+        assert_eq!(protocol!(CustomProtocol), proto);
+        assert_eq!(get_protocol!(CustomProtocol), Some(proto));
+        assert!(get_protocol!(NonExistantProtocol).is_none());
         let class = test_utils::custom_class();
         assert!(class.conforms_to(proto));
         #[cfg(feature = "malloc")]
         assert!(class.adopted_protocols().len() > 0);
     }
 
+    #[test]
+    fn test_protocol_method_descriptions() {
+        let proto = test_utils::custom_protocol();
+
+        let desc = proto.method_description(sel!(setBar:), true, true).unwrap();
+        assert_eq!(desc.name(), sel!(setBar:));
+        // Returns void
+        assert!(desc.type_encoding().starts_with('v'));
+        assert!(proto
+            .method_description(sel!(setBar:), false, true)
+            .is_none());
+        assert!(proto
+            .method_description(sel!(setBar:), true, false)
+            .is_none());
+        assert!(proto
+            .method_description(sel!(addNumber:toNumber:), true, false)
+            .is_some());
+        assert!(proto
+            .method_description(sel!(getName), false, true)
+            .is_some());
+
+        // Inherited methods are found too
+        let sub_proto = test_utils::custom_subprotocol();
+        assert!(sub_proto
+            .method_description(sel!(setBar:), true, true)
+            .is_some());
+
+        #[cfg(feature = "malloc")]
+        {
+            let descs = proto.method_descriptions(true, true);
+            assert_eq!(descs, [desc]);
+            let descs = proto.method_descriptions(false, true);
+            assert_eq!(descs.len(), 1);
+            assert_eq!(descs[0].name(), sel!(getName));
+            assert!(sub_proto
+                .method_descriptions(true, true)
+                .iter()
+                .all(|desc| desc.name() != sel!(setBar:)));
+        }
+    }
+
+    #[test]
+    fn test_protocol_properties() {
+        let proto = test_utils::custom_protocol();
+        let property = proto.property("bar", true, true).unwrap();
+        assert_eq!(property.name(), "bar");
+        assert!(proto.property("bar", false, true).is_none());
+        assert!(proto.property("foo", true, true).is_none());
+
+        #[cfg(feature = "malloc")]
+        {
+            let properties = proto.properties();
+            assert_eq!(properties.len(), 1);
+            assert_eq!(properties[0].name(), "bar");
+        }
+    }
+
     #[test]
     fn test_protocol_method() {
         let class = test_utils::custom_class();
@@ -718,6 +1195,25 @@ mod tests {
         assert_eq!(result, 4);
     }
 
+    #[test]
+    fn test_set_class() {
+        let obj = test_utils::custom_object();
+        let subclass = test_utils::custom_subclass();
+        let previous = unsafe { obj.set_class(subclass) };
+        assert_eq!(previous, test_utils::custom_class());
+        assert_eq!(obj.class(), subclass);
+
+        let result: u32 = unsafe { msg_send![obj, foo] };
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    #[should_panic = "the instance sizes differ"]
+    fn test_set_class_size_mismatch() {
+        let obj = test_utils::custom_object();
+        let _ = unsafe { obj.set_class(class!(NSObject)) };
+    }
+
     #[test]
     fn test_encode() {
         fn assert_enc<T: Encode>(expected: &str) {
@@ -739,6 +1235,8 @@ mod tests {
         assert_send_sync::<Class>();
         assert_send_sync::<Ivar>();
         assert_send_sync::<Method>();
+        assert_send_sync::<MethodDescription>();
+        assert_send_sync::<Property>();
         assert_send_sync::<Protocol>();
         assert_send_sync::<Sel>();
     }
@@ -2,7 +2,7 @@ use core::ops::{Deref, DerefMut};
 use std::os::raw::c_char;
 use std::sync::Once;
 
-use crate::declare::{ClassDecl, ProtocolDecl};
+use crate::declare::{ClassDecl, PropertyAttributes, ProtocolDecl};
 use crate::runtime::{Class, Object, Protocol, Sel};
 use crate::{ffi, Encode, Encoding, MessageReceiver};
 
@@ -12,7 +12,7 @@ pub(crate) struct CustomObject {
 }
 
 impl CustomObject {
-    fn new(class: &Class) -> Self {
+    pub(crate) fn new(class: &Class) -> Self {
         let ptr = class as *const Class as _;
         let obj = unsafe { ffi::class_createInstance(ptr, 0) };
         CustomObject { obj: obj as _ }
@@ -80,6 +80,14 @@ pub(crate) fn custom_class() -> &'static Class {
 
         decl.add_protocol(proto);
         decl.add_ivar::<u32>("_foo");
+        decl.add_property::<u32>(
+            "foo",
+            PropertyAttributes {
+                nonatomic: true,
+                ivar: Some("_foo"),
+                ..Default::default()
+            },
+        );
 
         extern "C" fn custom_obj_set_foo(this: &mut Object, _cmd: Sel, foo: u32) {
             unsafe {
@@ -104,9 +112,9 @@ pub(crate) fn custom_class() -> &'static Class {
             7
         }
 
-        extern "C" fn custom_obj_set_bar(this: &mut Object, _cmd: Sel, bar: u32) {
+        extern "C" fn custom_obj_set_bar(this: &mut Object, _cmd: Sel, bar: i32) {
             unsafe {
-                this.set_ivar::<u32>("_foo", bar);
+                this.set_ivar::<u32>("_foo", bar as u32);
             }
         }
 
@@ -129,7 +137,7 @@ pub(crate) fn custom_class() -> &'static Class {
             let class_method: extern "C" fn(&Class, Sel) -> u32 = custom_obj_class_method;
             decl.add_class_method(sel!(classFoo), class_method);
 
-            let protocol_instance_method: extern "C" fn(&mut Object, Sel, u32) = custom_obj_set_bar;
+            let protocol_instance_method: extern "C" fn(&mut Object, Sel, i32) = custom_obj_set_bar;
             decl.add_method(sel!(setBar:), protocol_instance_method);
             let protocol_class_method: extern "C" fn(&Class, Sel, i32, i32) -> i32 =
                 custom_obj_add_number_to_number;
@@ -151,6 +159,14 @@ pub(crate) fn custom_protocol() -> &'static Protocol {
         decl.add_method_description::<(i32,), ()>(sel!(setBar:), true);
         decl.add_method_description::<(), *const c_char>(sel!(getName), false);
         decl.add_class_method_description::<(i32, i32), i32>(sel!(addNumber:toNumber:), true);
+        decl.add_property::<u32>(
+            "bar",
+            PropertyAttributes {
+                readonly: true,
+                ..Default::default()
+            },
+            true,
+        );
 
         decl.register();
     });
@@ -174,6 +190,24 @@ pub(crate) fn custom_subprotocol() -> &'static Protocol {
     Protocol::get("CustomSubProtocol").unwrap()
 }
 
+/// A protocol for testing verification of declared classes, where
+/// `CustomObject` implements `foo` with the described types, but not
+/// `calculateFoo:`.
+pub(crate) fn custom_verify_protocol() -> &'static Protocol {
+    static REGISTER_CUSTOM_VERIFY_PROTOCOL: Once = Once::new();
+
+    REGISTER_CUSTOM_VERIFY_PROTOCOL.call_once(|| {
+        let mut decl = ProtocolDecl::new("CustomVerifyProtocol").unwrap();
+
+        decl.add_method_description::<(), u32>(sel!(foo), true);
+        decl.add_method_description::<(u32,), u32>(sel!(calculateFoo:), true);
+
+        decl.register();
+    });
+
+    Protocol::get("CustomVerifyProtocol").unwrap()
+}
+
 pub(crate) fn custom_object() -> CustomObject {
     CustomObject::new(custom_class())
 }
@@ -0,0 +1,206 @@
+//! Tracing of message sends.
+//!
+//! When a sink has been installed with [`set_sink`], every message sent with
+//! [`msg_send!`] and friends (or the methods on [`MessageReceiver`]) is
+//! reported to it after it returns, along with the receiver's class, the
+//! selector, the type encodings and how long the message took. This is
+//! useful for e.g. comparing which messages are sent on different platforms
+//! or runtimes, without attaching a debugger to `objc_msgSend`.
+//!
+//! The sink is a plain closure, so it can forward the traces to a logging
+//! framework like `log` or `tracing`, collect them, or print them.
+//!
+//! Messages sent from inside the sink are not traced, and neither are calls
+//! through a [`CachedImp`] or messages that failed verification.
+//!
+//! This is only available when the `trace_message` feature is enabled.
+//!
+//! [`msg_send!`]: crate::msg_send
+//! [`MessageReceiver`]: crate::MessageReceiver
+//! [`CachedImp`]: crate::CachedImp
+//!
+//! # Example
+//!
+//! ```no_run
+//! use objc2::trace;
+//!
+//! trace::set_sink(Some(Box::new(|trace| eprintln!("{}", trace))));
+//! // ... send messages
+//! trace::set_sink(None);
+//! ```
+
+use alloc::boxed::Box;
+use core::cell::Cell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use crate::runtime::{Class, Object, Sel};
+use crate::{Encode, EncodeArguments, Encoding, SendError};
+
+/// A message that was sent.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MessageTrace<'a> {
+    /// The class of the receiver, or [`None`] if the receiver was `nil`.
+    ///
+    /// For messages to classes, this is the metaclass.
+    pub class: Option<&'a Class>,
+    /// The superclass that the method was looked up on, if the message was
+    /// sent to `super`.
+    pub superclass: Option<&'a Class>,
+    /// The selector of the message.
+    pub sel: Sel,
+    /// The encodings of the (fixed) arguments.
+    pub arguments: &'static [Encoding<'static>],
+    /// The encoding of the return type.
+    pub ret: Encoding<'static>,
+    /// Whether the message threw an exception that was caught (only
+    /// possible with the `catch_all` feature).
+    pub failed: bool,
+    /// How long the message took.
+    pub duration: Duration,
+}
+
+impl fmt::Display for MessageTrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.class, self.superclass) {
+            (Some(cls), Some(superclass)) => write!(f, "[super({:?}, {:?})", cls, superclass)?,
+            (Some(cls), None) if cls.is_metaclass() => write!(f, "+[{:?}", cls)?,
+            (Some(cls), None) => write!(f, "-[{:?}", cls)?,
+            (None, _) => write!(f, "[nil")?,
+        }
+        write!(f, " {:?}] (", self.sel)?;
+        for (i, arg) in self.arguments.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        write!(f, ") -> {}", self.ret)?;
+        if self.failed {
+            write!(f, " threw")?;
+        }
+        write!(f, " in {:?}", self.duration)
+    }
+}
+
+type Sink = Box<dyn Fn(&MessageTrace<'_>) + Send + Sync>;
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+// Checked before taking the lock, so that sends are cheap when no sink is
+// installed.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+std::thread_local! {
+    static IN_SINK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets the closure that message sends are reported to, replacing the
+/// previous one, or stops tracing if [`None`] is given.
+///
+/// The closure may be called from any thread that sends messages, and must
+/// not call this function itself (that would deadlock).
+pub fn set_sink(sink: Option<Sink>) {
+    let mut current = SINK.write().unwrap_or_else(|e| e.into_inner());
+    ENABLED.store(sink.is_some(), Ordering::Relaxed);
+    *current = sink;
+}
+
+/// Resets the flag if the sink panics.
+struct InSink;
+
+impl Drop for InSink {
+    fn drop(&mut self) {
+        IN_SINK.with(|in_sink| in_sink.set(false));
+    }
+}
+
+/// Sends the message with `send`, and reports it to the sink.
+///
+/// # Safety
+///
+/// The receiver must be a valid object or NULL.
+#[inline]
+pub(crate) unsafe fn trace<A, R>(
+    receiver: *mut Object,
+    superclass: Option<&Class>,
+    sel: Sel,
+    send: impl FnOnce() -> Result<R, SendError>,
+) -> Result<R, SendError>
+where
+    A: EncodeArguments,
+    R: Encode,
+{
+    if !ENABLED.load(Ordering::Relaxed) || IN_SINK.with(Cell::get) {
+        return send();
+    }
+
+    // Looked up before sending, since the message may deallocate the
+    // receiver.
+    // SAFETY: Upheld by the caller.
+    let class = unsafe { receiver.as_ref() }.map(Object::class);
+    let start = Instant::now();
+    let result = send();
+    let duration = start.elapsed();
+
+    let trace = MessageTrace {
+        class,
+        superclass,
+        sel,
+        arguments: A::ENCODINGS,
+        ret: R::ENCODING,
+        failed: result.is_err(),
+        duration,
+    };
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = &*sink {
+        IN_SINK.with(|in_sink| in_sink.set(true));
+        let _guard = InSink;
+        sink(&trace);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_trace() {
+        let traces: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink_traces = traces.clone();
+        let obj = test_utils::custom_object();
+        let cls = test_utils::custom_class();
+        let thread = std::thread::current().id();
+
+        set_sink(Some(Box::new(move |trace| {
+            // Other tests may send messages concurrently
+            if std::thread::current().id() == thread {
+                // Messages sent in the sink are not traced
+                let _: u32 = unsafe { msg_send![cls, classFoo] };
+                sink_traces.lock().unwrap().push(trace.to_string());
+            }
+        })));
+        unsafe {
+            let _: () = msg_send![obj, setFoo: 4u32];
+            let _: u32 = msg_send![obj, foo];
+            let _: u32 = msg_send![cls, classFoo];
+        }
+        set_sink(None);
+        let _: u32 = unsafe { msg_send![obj, foo] };
+
+        let traces = traces.lock().unwrap();
+        assert_eq!(traces.len(), 3, "{:?}", traces);
+        assert!(traces[0].starts_with("-[CustomObject setFoo:] (I) -> v in "));
+        assert!(traces[1].starts_with("-[CustomObject foo] () -> I in "));
+        assert!(traces[2].starts_with("+[CustomObject classFoo] () -> I in "));
+    }
+}